@@ -0,0 +1,790 @@
+//! Checks workloads against the link's security rules before they are applied, modelled on the
+//! Pod Security Standards. Pods are checked directly and every other workload through its pod
+//! template
+
+use std::collections::HashMap;
+
+use kubernetes_applier_interface::PolicyViolation;
+use serde_json::Value;
+use wasmbus_rpc::error::RpcError;
+
+use crate::images;
+
+/// The Pod Security Standards level workloads must meet before they are applied: `baseline` or
+/// `restricted`
+const POD_SECURITY_KEY: &str = "pod_security";
+/// A comma delimited list of registries (e.g. `ghcr.io`) or repository prefixes (e.g.
+/// `ghcr.io/cosmonic`) that workload images must come from
+const ALLOWED_REGISTRIES_KEY: &str = "allowed_registries";
+
+/// The fields containers are listed in
+const CONTAINER_FIELDS: &[&str] = &["initContainers", "containers", "ephemeralContainers"];
+/// The host namespace fields disallowed by the baseline level
+const HOST_NAMESPACE_FIELDS: &[&str] = &["hostNetwork", "hostPID", "hostIPC"];
+/// The capabilities containers may add at the baseline level
+const BASELINE_CAPABILITIES: &[&str] = &[
+    "AUDIT_WRITE",
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "MKNOD",
+    "NET_BIND_SERVICE",
+    "SETFCAP",
+    "SETGID",
+    "SETPCAP",
+    "SETUID",
+    "SYS_CHROOT",
+];
+/// The capabilities containers may add at the restricted level
+const RESTRICTED_CAPABILITIES: &[&str] = &["NET_BIND_SERVICE"];
+/// The seccomp and AppArmor profile types allowed by the baseline level, which the restricted level
+/// also requires to be set
+const ALLOWED_PROFILE_TYPES: &[&str] = &["RuntimeDefault", "Localhost"];
+/// The prefix of the annotations setting a container's AppArmor profile
+const APPARMOR_ANNOTATION_PREFIX: &str = "container.apparmor.security.beta.kubernetes.io/";
+/// The SELinux types allowed by the baseline level
+const ALLOWED_SELINUX_TYPES: &[&str] = &[
+    "",
+    "container_t",
+    "container_init_t",
+    "container_kvm_t",
+    "container_engine_t",
+];
+/// The sysctls allowed by the baseline level
+const SAFE_SYSCTLS: &[&str] = &[
+    "kernel.shm_rmid_forced",
+    "net.ipv4.ip_local_port_range",
+    "net.ipv4.ip_unprivileged_port_start",
+    "net.ipv4.tcp_syncookies",
+    "net.ipv4.ping_group_range",
+    "net.ipv4.ip_local_reserved_ports",
+    "net.ipv4.tcp_keepalive_time",
+    "net.ipv4.tcp_fin_timeout",
+    "net.ipv4.tcp_keepalive_intvl",
+    "net.ipv4.tcp_keepalive_probes",
+];
+/// The volume types allowed by the restricted level
+const RESTRICTED_VOLUME_TYPES: &[&str] = &[
+    "configMap",
+    "csi",
+    "downwardAPI",
+    "emptyDir",
+    "ephemeral",
+    "persistentVolumeClaim",
+    "projected",
+    "secret",
+];
+
+/// The Pod Security Standards levels workloads can be held to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PodSecurityLevel {
+    /// Every control of the baseline level: no host process, host namespaces, privileged
+    /// containers, host path volumes or host ports, only the allowed capabilities, AppArmor,
+    /// SELinux, seccomp profiles and sysctls, and the default `/proc` mount
+    Baseline,
+    /// Baseline and every control of the restricted level: only the allowed volume types, no
+    /// privilege escalation, running as a non-root user, a `RuntimeDefault` or `Localhost` seccomp
+    /// profile and dropping all capabilities but `NET_BIND_SERVICE`
+    Restricted,
+}
+
+/// Settings for the checks workloads must pass before they are applied
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdmissionSettings {
+    pub pod_security: Option<PodSecurityLevel>,
+    pub allowed_registries: Vec<String>,
+}
+
+impl AdmissionSettings {
+    /// Parses the admission settings from the given (lowercased) link values. Returns `None` if no
+    /// checks are enabled
+    pub fn from_values(
+        values: &HashMap<String, String>,
+    ) -> Result<Option<AdmissionSettings>, RpcError> {
+        let pod_security = match values.get(POD_SECURITY_KEY).map(|s| s.trim()) {
+            None | Some("privileged") => None,
+            Some("baseline") => Some(PodSecurityLevel::Baseline),
+            Some("restricted") => Some(PodSecurityLevel::Restricted),
+            Some(other) => {
+                return Err(RpcError::ProviderInit(format!(
+                    "Invalid value {} for {}, must be privileged, baseline or restricted",
+                    other, POD_SECURITY_KEY
+                )))
+            }
+        };
+        let allowed_registries: Vec<String> = values
+            .get(ALLOWED_REGISTRIES_KEY)
+            .map(|raw| {
+                raw.split(',')
+                    .map(|s| s.trim().trim_end_matches('/').to_owned())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if values.contains_key(ALLOWED_REGISTRIES_KEY) && allowed_registries.is_empty() {
+            return Err(RpcError::ProviderInit(format!(
+                "{} must contain at least one registry",
+                ALLOWED_REGISTRIES_KEY
+            )));
+        }
+        if pod_security.is_none() && allowed_registries.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(AdmissionSettings {
+            pod_security,
+            allowed_registries,
+        }))
+    }
+}
+
+/// Checks the object against the given settings, returning every violation found. Objects that don't
+/// contain a pod spec always pass
+pub fn check(settings: &AdmissionSettings, object: &Value) -> Vec<PolicyViolation> {
+    let (path, spec) = match pod_spec(object) {
+        Some(found) => found,
+        None => return Vec::new(),
+    };
+    let mut violations = Vec::new();
+    if let Some(level) = settings.pod_security {
+        let capabilities = if level >= PodSecurityLevel::Restricted {
+            RESTRICTED_CAPABILITIES
+        } else {
+            BASELINE_CAPABILITIES
+        };
+        check_baseline(object, spec, path, capabilities, &mut violations);
+        if level >= PodSecurityLevel::Restricted {
+            check_restricted(spec, path, &mut violations);
+        }
+    }
+    if !settings.allowed_registries.is_empty() {
+        for_each_container(spec, path, |container, field| {
+            let image = container
+                .get("image")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let normalized = images::normalize(image);
+            let allowed = settings.allowed_registries.iter().any(|allowed| {
+                normalized == *allowed || normalized.starts_with(&format!("{}/", allowed))
+            });
+            if !allowed {
+                violations.push(violation(
+                    "allowedRegistries",
+                    format!("{}.image", field),
+                    format!("Image {} is not from an allowed registry", image),
+                ));
+            }
+        });
+    }
+    violations
+}
+
+/// Returns the pod spec of the object along with its path, if the object is a pod or a workload
+/// with a pod template
+fn pod_spec(object: &Value) -> Option<(&'static str, &Value)> {
+    let path = pod_spec_path(object)?;
+    let spec = path
+        .split('.')
+        .try_fold(object, |value, key| value.get(key))?;
+    Some((path, spec))
+}
+
+/// Returns the dot separated path of the pod spec in objects of the same kind as the given one, if
+/// it is a pod or a workload with a pod template
+pub fn pod_spec_path(object: &Value) -> Option<&'static str> {
+    let path = match object.get("kind")?.as_str()? {
+        "Pod" => "spec",
+        "PodTemplate" => "template.spec",
+        "Deployment"
+        | "StatefulSet"
+        | "DaemonSet"
+        | "ReplicaSet"
+        | "ReplicationController"
+        | "Job" => "spec.template.spec",
+        "CronJob" => "spec.jobTemplate.spec.template.spec",
+        _ => return None,
+    };
+    Some(path)
+}
+
+/// Checks the baseline controls, with containers only being allowed to add the given capabilities
+fn check_baseline(
+    object: &Value,
+    spec: &Value,
+    path: &str,
+    capabilities: &[&str],
+    violations: &mut Vec<PolicyViolation>,
+) {
+    for_each_security_context(spec, path, |context, field| {
+        if is_true(context.pointer("/windowsOptions/hostProcess")) {
+            violations.push(violation(
+                "hostProcess",
+                format!("{}.windowsOptions.hostProcess", field),
+                "Windows host process containers are not allowed".to_owned(),
+            ));
+        }
+        for profile in ["seccompProfile", "appArmorProfile"] {
+            let kind = context
+                .get(profile)
+                .and_then(|p| p.get("type"))
+                .and_then(Value::as_str);
+            if let Some(kind) = kind.filter(|k| !ALLOWED_PROFILE_TYPES.contains(k)) {
+                violations.push(violation(
+                    profile,
+                    format!("{}.{}.type", field, profile),
+                    format!("The {} profile type is not allowed", kind),
+                ));
+            }
+        }
+        if let Some(options) = context.get("seLinuxOptions") {
+            let kind = options.get("type").and_then(Value::as_str);
+            if let Some(kind) = kind.filter(|k| !ALLOWED_SELINUX_TYPES.contains(k)) {
+                violations.push(violation(
+                    "seLinuxOptions",
+                    format!("{}.seLinuxOptions.type", field),
+                    format!("The SELinux type {} is not allowed", kind),
+                ));
+            }
+            for key in ["user", "role"] {
+                if !options
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .is_empty()
+                {
+                    violations.push(violation(
+                        "seLinuxOptions",
+                        format!("{}.seLinuxOptions.{}", field, key),
+                        format!("The SELinux {} must not be set", key),
+                    ));
+                }
+            }
+        }
+    });
+    for (i, sysctl) in array(spec.pointer("/securityContext/sysctls"))
+        .iter()
+        .enumerate()
+    {
+        let name = sysctl
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if !SAFE_SYSCTLS.contains(&name) {
+            violations.push(violation(
+                "sysctls",
+                format!("{}.securityContext.sysctls[{}].name", path, i),
+                format!("The sysctl {} is not allowed", name),
+            ));
+        }
+    }
+    // The spec is always the `spec` of the object or template holding the metadata
+    let metadata_path = format!("{}metadata", path.trim_end_matches("spec"));
+    let annotations = metadata_path
+        .split('.')
+        .try_fold(object, |value, key| value.get(key))
+        .and_then(|metadata| metadata.get("annotations"))
+        .and_then(Value::as_object);
+    for (key, value) in annotations.into_iter().flatten() {
+        let profile = value.as_str().unwrap_or_default();
+        if key.starts_with(APPARMOR_ANNOTATION_PREFIX)
+            && profile != "runtime/default"
+            && !profile.starts_with("localhost/")
+        {
+            violations.push(violation(
+                "appArmorProfile",
+                format!("{}.annotations.{}", metadata_path, key),
+                format!("The AppArmor profile {} is not allowed", profile),
+            ));
+        }
+    }
+    for field in HOST_NAMESPACE_FIELDS {
+        if is_true(spec.get(field)) {
+            violations.push(violation(
+                "hostNamespaces",
+                format!("{}.{}", path, field),
+                format!("{} must not be set", field),
+            ));
+        }
+    }
+    for (i, volume) in array(spec.get("volumes")).iter().enumerate() {
+        if volume.get("hostPath").is_some() {
+            violations.push(violation(
+                "hostPathVolumes",
+                format!("{}.volumes[{}].hostPath", path, i),
+                "hostPath volumes are not allowed".to_owned(),
+            ));
+        }
+    }
+    for_each_container(spec, path, |container, field| {
+        if is_true(container.pointer("/securityContext/privileged")) {
+            violations.push(violation(
+                "privileged",
+                format!("{}.securityContext.privileged", field),
+                "Privileged containers are not allowed".to_owned(),
+            ));
+        }
+        for (i, port) in array(container.get("ports")).iter().enumerate() {
+            if port
+                .get("hostPort")
+                .and_then(Value::as_i64)
+                .unwrap_or_default()
+                != 0
+            {
+                violations.push(violation(
+                    "hostPorts",
+                    format!("{}.ports[{}].hostPort", field, i),
+                    "hostPort must not be set".to_owned(),
+                ));
+            }
+        }
+        let proc_mount = container
+            .pointer("/securityContext/procMount")
+            .and_then(Value::as_str);
+        if proc_mount.is_some_and(|m| m != "Default") {
+            violations.push(violation(
+                "procMount",
+                format!("{}.securityContext.procMount", field),
+                "Only the Default /proc mount is allowed".to_owned(),
+            ));
+        }
+        check_added_capabilities(container, &field, capabilities, violations);
+    });
+}
+
+/// Checks the restricted controls, on top of the baseline ones
+fn check_restricted(spec: &Value, path: &str, violations: &mut Vec<PolicyViolation>) {
+    for (i, volume) in array(spec.get("volumes")).iter().enumerate() {
+        let kinds = volume.as_object().into_iter().flatten().map(|(k, _)| k);
+        // hostPath volumes were already reported by the baseline check
+        let disallowed = |k: &&String| {
+            !["name", "hostPath"].contains(&k.as_str())
+                && !RESTRICTED_VOLUME_TYPES.contains(&k.as_str())
+        };
+        for kind in kinds.filter(disallowed) {
+            violations.push(violation(
+                "volumeTypes",
+                format!("{}.volumes[{}].{}", path, i, kind),
+                format!("{} volumes are not allowed", kind),
+            ));
+        }
+    }
+    for_each_security_context(spec, path, |context, field| {
+        if context.get("runAsUser").and_then(Value::as_i64) == Some(0) {
+            violations.push(violation(
+                "runAsUser",
+                format!("{}.runAsUser", field),
+                "Running as the root user (0) is not allowed".to_owned(),
+            ));
+        }
+    });
+    // Containers inherit the pod's setting unless they set their own
+    let pod_non_root = is_true(spec.pointer("/securityContext/runAsNonRoot"));
+    for_each_container(spec, path, |container, field| {
+        let non_root = match container.pointer("/securityContext/runAsNonRoot") {
+            Some(value) => is_true(Some(value)),
+            None => pod_non_root,
+        };
+        if !non_root {
+            violations.push(violation(
+                "runAsNonRoot",
+                format!("{}.securityContext.runAsNonRoot", field),
+                "Containers must set runAsNonRoot to true, either on the pod or the container"
+                    .to_owned(),
+            ));
+        }
+        if container.pointer("/securityContext/allowPrivilegeEscalation")
+            != Some(&Value::Bool(false))
+        {
+            violations.push(violation(
+                "allowPrivilegeEscalation",
+                format!("{}.securityContext.allowPrivilegeEscalation", field),
+                "Containers must set allowPrivilegeEscalation to false".to_owned(),
+            ));
+        }
+        let drops_all = array(container.pointer("/securityContext/capabilities/drop"))
+            .iter()
+            .any(|c| c.as_str() == Some("ALL"));
+        if !drops_all {
+            violations.push(violation(
+                "capabilities",
+                format!("{}.securityContext.capabilities.drop", field),
+                "Containers must drop ALL capabilities".to_owned(),
+            ));
+        }
+        // Profiles that are set but not allowed were already reported by the baseline check
+        let seccomp = container
+            .pointer("/securityContext/seccompProfile/type")
+            .or_else(|| spec.pointer("/securityContext/seccompProfile/type"));
+        if seccomp.is_none() {
+            violations.push(violation(
+                "seccompProfile",
+                format!("{}.securityContext.seccompProfile.type", field),
+                "Containers must use the RuntimeDefault or Localhost seccomp profile, either set on \
+                 the pod or the container"
+                    .to_owned(),
+            ));
+        }
+    });
+}
+
+/// Adds a violation for every capability the container adds that isn't in the allowed list
+fn check_added_capabilities(
+    container: &Value,
+    field: &str,
+    allowed: &[&str],
+    violations: &mut Vec<PolicyViolation>,
+) {
+    let added = array(container.pointer("/securityContext/capabilities/add"));
+    for (i, capability) in added.iter().enumerate() {
+        let capability = capability.as_str().unwrap_or_default();
+        if !allowed.contains(&capability) {
+            violations.push(violation(
+                "capabilities",
+                format!("{}.securityContext.capabilities.add[{}]", field, i),
+                format!("Adding the {} capability is not allowed", capability),
+            ));
+        }
+    }
+}
+
+/// Calls the given function with the security context of the pod and of every container that has
+/// one, along with its path
+fn for_each_security_context(spec: &Value, path: &str, mut f: impl FnMut(&Value, String)) {
+    if let Some(context) = spec.get("securityContext") {
+        f(context, format!("{}.securityContext", path));
+    }
+    for_each_container(spec, path, |container, field| {
+        if let Some(context) = container.get("securityContext") {
+            f(context, format!("{}.securityContext", field));
+        }
+    });
+}
+
+/// Calls the given function with every container in the pod spec and its path
+fn for_each_container(spec: &Value, path: &str, mut f: impl FnMut(&Value, String)) {
+    for list in CONTAINER_FIELDS {
+        for (i, container) in array(spec.get(list)).iter().enumerate() {
+            f(container, format!("{}.{}[{}]", path, list, i));
+        }
+    }
+}
+
+fn array(value: Option<&Value>) -> &[Value] {
+    value
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn is_true(value: Option<&Value>) -> bool {
+    value.and_then(Value::as_bool).unwrap_or_default()
+}
+
+fn violation(policy: &str, field: String, message: String) -> PolicyViolation {
+    PolicyViolation {
+        policy: policy.to_owned(),
+        field,
+        message,
+    }
+}
+
+/// Returns a single message describing all of the given violations
+pub fn describe(violations: &[PolicyViolation]) -> String {
+    violations
+        .iter()
+        .map(|v| format!("{} ({}): {}", v.field, v.policy, v.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::test::values;
+
+    use super::*;
+
+    fn parse(manifest: &str) -> Value {
+        serde_yaml::from_str(manifest).unwrap()
+    }
+
+    fn policies(violations: &[PolicyViolation]) -> Vec<(&str, String)> {
+        violations
+            .iter()
+            .map(|v| (v.policy.as_str(), v.field.clone()))
+            .collect()
+    }
+
+    const CRONJOB: &str = r#"
+apiVersion: batch/v1
+kind: CronJob
+metadata:
+  name: backup
+spec:
+  schedule: "0 * * * *"
+  jobTemplate:
+    spec:
+      template:
+        spec:
+          hostNetwork: true
+          securityContext:
+            runAsNonRoot: true
+            seccompProfile:
+              type: RuntimeDefault
+          volumes:
+            - name: data
+              hostPath:
+                path: /var/data
+          containers:
+            - name: backup
+              image: ghcr.io/cosmonic/backup:1.0
+              ports:
+                - containerPort: 8080
+                  hostPort: 8080
+              securityContext:
+                privileged: true
+                capabilities:
+                  add: ["NET_ADMIN"]
+            - name: sidecar
+              image: nginx:1.23
+              securityContext:
+                runAsNonRoot: false
+                allowPrivilegeEscalation: false
+                capabilities:
+                  drop: ["ALL"]
+                  add: ["NET_BIND_SERVICE", "CHOWN"]
+                seccompProfile:
+                  type: Unconfined
+"#;
+
+    #[test]
+    fn pod_security() {
+        let object = parse(CRONJOB);
+        let baseline = AdmissionSettings {
+            pod_security: Some(PodSecurityLevel::Baseline),
+            allowed_registries: Vec::new(),
+        };
+        let violations = check(&baseline, &object);
+        let containers = "spec.jobTemplate.spec.template.spec.containers";
+        assert_eq!(
+            policies(&violations),
+            vec![
+                (
+                    "seccompProfile",
+                    format!("{}[1].securityContext.seccompProfile.type", containers)
+                ),
+                (
+                    "hostNamespaces",
+                    "spec.jobTemplate.spec.template.spec.hostNetwork".to_owned()
+                ),
+                (
+                    "hostPathVolumes",
+                    "spec.jobTemplate.spec.template.spec.volumes[0].hostPath".to_owned()
+                ),
+                (
+                    "privileged",
+                    format!("{}[0].securityContext.privileged", containers)
+                ),
+                ("hostPorts", format!("{}[0].ports[0].hostPort", containers)),
+                (
+                    "capabilities",
+                    format!("{}[0].securityContext.capabilities.add[0]", containers)
+                ),
+            ]
+        );
+
+        let restricted = AdmissionSettings {
+            pod_security: Some(PodSecurityLevel::Restricted),
+            ..baseline
+        };
+        let violations = check(&restricted, &object);
+        assert_eq!(
+            policies(&violations[4..]),
+            vec![
+                ("hostPorts", format!("{}[0].ports[0].hostPort", containers)),
+                (
+                    "capabilities",
+                    format!("{}[0].securityContext.capabilities.add[0]", containers)
+                ),
+                // Only NET_BIND_SERVICE may be added
+                (
+                    "capabilities",
+                    format!("{}[1].securityContext.capabilities.add[1]", containers)
+                ),
+                (
+                    "allowPrivilegeEscalation",
+                    format!("{}[0].securityContext.allowPrivilegeEscalation", containers)
+                ),
+                (
+                    "capabilities",
+                    format!("{}[0].securityContext.capabilities.drop", containers)
+                ),
+                (
+                    "runAsNonRoot",
+                    format!("{}[1].securityContext.runAsNonRoot", containers)
+                ),
+            ]
+        );
+
+        let compliant = parse(
+            r#"
+apiVersion: v1
+kind: Pod
+metadata:
+  name: web
+spec:
+  securityContext:
+    runAsNonRoot: true
+  containers:
+    - name: web
+      image: nginx:1.23
+      securityContext:
+        allowPrivilegeEscalation: false
+        capabilities:
+          drop: ["ALL"]
+        seccompProfile:
+          type: RuntimeDefault
+"#,
+        );
+        assert!(check(&restricted, &compliant).is_empty());
+
+        let config_map = parse("apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: foo\n");
+        assert!(check(&restricted, &config_map).is_empty());
+    }
+
+    #[test]
+    fn other_controls() {
+        let pod = parse(
+            r#"
+apiVersion: v1
+kind: Pod
+metadata:
+  name: web
+  annotations:
+    container.apparmor.security.beta.kubernetes.io/web: unconfined
+    container.apparmor.security.beta.kubernetes.io/sidecar: localhost/web
+spec:
+  securityContext:
+    runAsUser: 0
+    runAsNonRoot: true
+    seccompProfile:
+      type: RuntimeDefault
+    sysctls:
+      - name: net.ipv4.tcp_syncookies
+        value: "1"
+      - name: kernel.msgmax
+        value: "65536"
+    seLinuxOptions:
+      type: spc_t
+  volumes:
+    - name: data
+      nfs:
+        server: nfs.local
+        path: /data
+    - name: config
+      configMap:
+        name: web
+  containers:
+    - name: web
+      image: nginx:1.23
+      securityContext:
+        allowPrivilegeEscalation: false
+        capabilities:
+          drop: ["ALL"]
+        procMount: Unmasked
+        seLinuxOptions:
+          user: system_u
+        windowsOptions:
+          hostProcess: true
+        appArmorProfile:
+          type: Unconfined
+"#,
+        );
+        let baseline = AdmissionSettings {
+            pod_security: Some(PodSecurityLevel::Baseline),
+            allowed_registries: Vec::new(),
+        };
+        let container = "spec.containers[0].securityContext";
+        let expected = vec![
+            (
+                "seLinuxOptions",
+                "spec.securityContext.seLinuxOptions.type".to_owned(),
+            ),
+            (
+                "hostProcess",
+                format!("{}.windowsOptions.hostProcess", container),
+            ),
+            (
+                "appArmorProfile",
+                format!("{}.appArmorProfile.type", container),
+            ),
+            (
+                "seLinuxOptions",
+                format!("{}.seLinuxOptions.user", container),
+            ),
+            ("sysctls", "spec.securityContext.sysctls[1].name".to_owned()),
+            (
+                "appArmorProfile",
+                "metadata.annotations.container.apparmor.security.beta.kubernetes.io/web"
+                    .to_owned(),
+            ),
+            ("procMount", format!("{}.procMount", container)),
+        ];
+        assert_eq!(policies(&check(&baseline, &pod)), expected);
+
+        let restricted = AdmissionSettings {
+            pod_security: Some(PodSecurityLevel::Restricted),
+            ..baseline
+        };
+        let violations = check(&restricted, &pod);
+        assert_eq!(
+            policies(&violations[expected.len()..]),
+            vec![
+                ("volumeTypes", "spec.volumes[0].nfs".to_owned()),
+                ("runAsUser", "spec.securityContext.runAsUser".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn registries() {
+        let settings = AdmissionSettings {
+            pod_security: None,
+            allowed_registries: vec!["ghcr.io/cosmonic".to_owned()],
+        };
+        let violations = check(&settings, &parse(CRONJOB));
+        assert_eq!(
+            policies(&violations),
+            vec![(
+                "allowedRegistries",
+                "spec.jobTemplate.spec.template.spec.containers[1].image".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn settings() {
+        assert!(AdmissionSettings::from_values(&HashMap::new())
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            AdmissionSettings::from_values(&values(&[
+                ("pod_security", "restricted"),
+                (
+                    "allowed_registries",
+                    "ghcr.io, registry.example.com/team/ ,"
+                )
+            ]))
+            .unwrap(),
+            Some(AdmissionSettings {
+                pod_security: Some(PodSecurityLevel::Restricted),
+                allowed_registries: vec![
+                    "ghcr.io".to_owned(),
+                    "registry.example.com/team".to_owned()
+                ],
+            })
+        );
+        assert!(AdmissionSettings::from_values(&values(&[("pod_security", "strict")])).is_err());
+        assert!(AdmissionSettings::from_values(&values(&[("allowed_registries", " ")])).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Tracking of what each link last applied so identical applies can be skipped
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use kube::api::DynamicObject;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// The annotation the desired state hash is stored in on every applied object
+pub const DESIRED_STATE_ANNOTATION: &str = "kubernetes-applier.cosmonic.com/desired-state-hash";
+
+/// The most objects a link remembers the applied state of. Past this the least recently used entry
+/// is dropped, which only means its next apply isn't skipped
+const MAX_ENTRIES: usize = 10_000;
+
+/// Metadata fields that are set by the server rather than the manifest author, so they are left out
+/// of the hash
+const SERVER_METADATA_FIELDS: &[&str] = &[
+    "creationTimestamp",
+    "generation",
+    "managedFields",
+    "resourceVersion",
+    "selfLink",
+    "uid",
+];
+
+/// Identifies a single object on a single cluster. The version is left out as the same object can be
+/// applied with different API versions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ObjectKey {
+    pub cluster: String,
+    pub group: String,
+    pub kind: String,
+    /// The namespace of the object, which is the cluster's default namespace if the object didn't
+    /// set one
+    pub namespace: String,
+    pub name: String,
+}
+
+impl ObjectKey {
+    /// Returns the key of an object, filling in the default namespace if none was given so that
+    /// an object applied with and without its namespace set gets the same key
+    pub fn new(
+        cluster: &str,
+        group: &str,
+        kind: &str,
+        namespace: Option<&str>,
+        default_namespace: &str,
+        name: &str,
+    ) -> ObjectKey {
+        ObjectKey {
+            cluster: cluster.to_owned(),
+            group: group.to_owned(),
+            kind: kind.to_owned(),
+            namespace: namespace.unwrap_or(default_namespace).to_owned(),
+            name: name.to_owned(),
+        }
+    }
+}
+
+/// What was last applied for an object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedState {
+    /// The desired state hash of the applied manifest
+    pub hash: String,
+    /// The resource version of the object returned by the apply
+    pub resource_version: Option<String>,
+}
+
+/// The entries of an [`AppliedCache`] along with the order they were last used in
+#[derive(Default)]
+struct Entries {
+    /// Each object's state and the tick it was last used at
+    objects: HashMap<ObjectKey, (AppliedState, u64)>,
+    /// The objects by the tick they were last used at, oldest first
+    used: BTreeMap<u64, ObjectKey>,
+    tick: u64,
+}
+
+impl Entries {
+    /// Marks the object as just used, returning its state
+    fn touch(&mut self, key: &ObjectKey) -> Option<AppliedState> {
+        let (state, last_used) = self.objects.get_mut(key)?;
+        self.tick += 1;
+        let key = self.used.remove(last_used)?;
+        *last_used = self.tick;
+        self.used.insert(self.tick, key);
+        Some(state.clone())
+    }
+
+    fn insert(&mut self, key: ObjectKey, state: AppliedState) {
+        self.tick += 1;
+        if let Some((_, last_used)) = self.objects.insert(key.clone(), (state, self.tick)) {
+            self.used.remove(&last_used);
+        }
+        self.used.insert(self.tick, key);
+        while self.objects.len() > MAX_ENTRIES {
+            match self.used.pop_first() {
+                Some((_, oldest)) => self.objects.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+
+    fn remove(&mut self, key: &ObjectKey) {
+        if let Some((_, last_used)) = self.objects.remove(key) {
+            self.used.remove(&last_used);
+        }
+    }
+}
+
+/// The last state applied for each object by a single link, holding at most [`MAX_ENTRIES`] of the
+/// most recently used objects. Cloning is cheap and all clones share the same state
+#[derive(Clone, Default)]
+pub struct AppliedCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl AppliedCache {
+    pub fn get(&self, key: &ObjectKey) -> Option<AppliedState> {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        self.entries.lock().unwrap().touch(key)
+    }
+
+    pub fn insert(&self, key: ObjectKey, state: AppliedState) {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        self.entries.lock().unwrap().insert(key, state);
+    }
+
+    pub fn remove(&self, key: &ObjectKey) {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+/// Computes a stable hash of the desired state in the given object. Key order, server set metadata
+/// and the hash annotation itself don't affect the result
+pub fn desired_state_hash(object: &DynamicObject) -> String {
+    // SAFETY: A DynamicObject is always representable as JSON
+    let mut value = serde_json::to_value(object).unwrap();
+    if let Some(metadata) = value.get_mut("metadata").and_then(Value::as_object_mut) {
+        for field in SERVER_METADATA_FIELDS {
+            metadata.remove(*field);
+        }
+        if let Some(annotations) = metadata
+            .get_mut("annotations")
+            .and_then(Value::as_object_mut)
+        {
+            annotations.remove(DESIRED_STATE_ANNOTATION);
+            if annotations.is_empty() {
+                metadata.remove("annotations");
+            }
+        }
+    }
+    if let Some(object) = value.as_object_mut() {
+        object.remove("status");
+    }
+
+    let mut hasher = Sha256::new();
+    hash_value(&value, &mut hasher);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Feeds the value to the hasher with object keys in sorted order
+fn hash_value(value: &Value, hasher: &mut Sha256) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            hasher.update(b"{");
+            for (key, value) in entries {
+                hasher.update(Value::String(key.clone()).to_string());
+                hasher.update(b":");
+                hash_value(value, hasher);
+                hasher.update(b",");
+            }
+            hasher.update(b"}");
+        }
+        Value::Array(items) => {
+            hasher.update(b"[");
+            for item in items {
+                hash_value(item, hasher);
+                hasher.update(b",");
+            }
+            hasher.update(b"]");
+        }
+        scalar => hasher.update(scalar.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(manifest: &str) -> DynamicObject {
+        serde_yaml::from_str(manifest).unwrap()
+    }
+
+    #[test]
+    fn hash_is_normalized() {
+        let first = parse(
+            r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: test
+  labels:
+    a: b
+    c: d
+data:
+  foo: bar
+"#,
+        );
+        let reordered = parse(
+            r#"
+kind: ConfigMap
+apiVersion: v1
+data:
+  foo: bar
+metadata:
+  labels:
+    c: d
+    a: b
+  name: test
+  resourceVersion: "1234"
+  annotations:
+    kubernetes-applier.cosmonic.com/desired-state-hash: abcd
+"#,
+        );
+        let changed = parse(
+            r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: test
+  labels:
+    a: b
+    c: d
+data:
+  foo: baz
+"#,
+        );
+        assert_eq!(desired_state_hash(&first), desired_state_hash(&reordered));
+        assert_ne!(desired_state_hash(&first), desired_state_hash(&changed));
+    }
+
+    #[test]
+    fn cache() {
+        let cache = AppliedCache::default();
+        let key = ObjectKey::new("default", "", "ConfigMap", None, "default", "test");
+        let state = AppliedState {
+            hash: "abcd".to_owned(),
+            resource_version: Some("1".to_owned()),
+        };
+        assert!(cache.get(&key).is_none());
+        cache.insert(key.clone(), state.clone());
+        assert_eq!(cache.get(&key), Some(state));
+        cache.remove(&key);
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let cache = AppliedCache::default();
+        let key =
+            |i: usize| ObjectKey::new("default", "", "ConfigMap", None, "default", &i.to_string());
+        let state = AppliedState {
+            hash: "abcd".to_owned(),
+            resource_version: None,
+        };
+        for i in 0..MAX_ENTRIES {
+            cache.insert(key(i), state.clone());
+        }
+        // Using the oldest entry keeps it around, so the next oldest is dropped instead
+        assert!(cache.get(&key(0)).is_some());
+        cache.insert(key(MAX_ENTRIES), state.clone());
+        assert!(cache.get(&key(0)).is_some());
+        assert!(cache.get(&key(1)).is_none());
+        assert!(cache.get(&key(MAX_ENTRIES)).is_some());
+        assert_eq!(cache.entries.lock().unwrap().objects.len(), MAX_ENTRIES);
+        assert_eq!(cache.entries.lock().unwrap().used.len(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn key_namespace() {
+        let explicit = ObjectKey::new(
+            "default",
+            "",
+            "ConfigMap",
+            Some("default"),
+            "default",
+            "test",
+        );
+        let implicit = ObjectKey::new("default", "", "ConfigMap", None, "default", "test");
+        assert_eq!(explicit, implicit);
+        let other = ObjectKey::new("default", "", "ConfigMap", Some("other"), "default", "test");
+        assert_ne!(explicit, other);
+    }
+}
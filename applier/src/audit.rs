@@ -0,0 +1,395 @@
+//! An append only log of every apply and delete, written as one JSON object per line. The log is
+//! rotated once it reaches its maximum size. Entries are written and synced to disk on a dedicated
+//! thread so requests never wait on the disk, and the queue is drained when the provider shuts down
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+use k8s_openapi::chrono::{SecondsFormat, Utc};
+use kubernetes_applier_interface::OperationResponse;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::error;
+use wasmbus_rpc::error::RpcError;
+
+use crate::{error::ErrorKind, settings::AuditSettings};
+
+/// How many entries can be waiting to be written before new ones are dropped
+const QUEUE_SIZE: usize = 4096;
+
+/// The request an entry is written for
+pub struct Request<'a> {
+    /// Either `Apply` or `Delete`
+    pub operation: &'a str,
+    pub actor_id: &'a str,
+    /// The name of the actor's link, if it has one
+    pub link_name: Option<&'a str>,
+    /// The type and name of the object, empty if the manifest couldn't be parsed
+    pub group: &'a str,
+    pub version: &'a str,
+    pub kind: &'a str,
+    pub namespace: Option<&'a str>,
+    pub name: &'a str,
+    /// The manifest as the actor sent it, for applies
+    pub manifest: Option<&'a [u8]>,
+}
+
+struct Inner {
+    /// The queue of lines to write, `None` once the log is closed
+    lines: Mutex<Option<SyncSender<Vec<u8>>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Writes entries to the audit log. Cloning is cheap and all clones write to the same file
+#[derive(Clone)]
+pub struct AuditLog {
+    inner: Arc<Inner>,
+}
+
+impl AuditLog {
+    /// Opens the log file, creating it if it doesn't exist, and starts the thread writing to it
+    pub fn open(settings: &AuditSettings) -> Result<AuditLog, RpcError> {
+        let writer = Writer::open(settings).map_err(|e| {
+            RpcError::ProviderInit(format!("Unable to open audit log {}: {}", settings.path, e))
+        })?;
+        let (lines, received) = mpsc::sync_channel(QUEUE_SIZE);
+        let writer = std::thread::Builder::new()
+            .name("audit-log".to_owned())
+            .spawn(move || write_lines(writer, received))
+            .map_err(|e| {
+                RpcError::ProviderInit(format!("Unable to start audit log writer: {}", e))
+            })?;
+        Ok(AuditLog {
+            inner: Arc::new(Inner {
+                lines: Mutex::new(Some(lines)),
+                writer: Mutex::new(Some(writer)),
+            }),
+        })
+    }
+
+    /// Queues an entry for the request with its outcome. Entries that can't be queued and failures
+    /// to write are logged, as there is nobody to return them to
+    pub fn record(&self, request: &Request<'_>, result: &Result<OperationResponse, RpcError>) {
+        let mut line = serde_json::to_vec(&entry(request, result))
+            .expect("audit entries are always representable as JSON");
+        line.push(b'\n');
+        // SAFETY: The lock is never held across an await or in code that can panic
+        let lines = self.inner.lines.lock().unwrap();
+        let sent = match lines.as_ref() {
+            Some(lines) => lines.try_send(line),
+            None => Err(TrySendError::Disconnected(line)),
+        };
+        match sent {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => error!(
+                queue_size = QUEUE_SIZE,
+                "Audit log queue is full as the disk can't keep up, dropping entry"
+            ),
+            Err(TrySendError::Disconnected(_)) => {
+                error!("Audit log writer has stopped, dropping entry")
+            }
+        }
+    }
+
+    /// Stops accepting entries and blocks until every queued entry is written and synced to disk
+    pub fn close(&self) {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        self.inner.lines.lock().unwrap().take();
+        // SAFETY: The lock is never held across an await or in code that can panic
+        let writer = self.inner.writer.lock().unwrap().take();
+        if let Some(writer) = writer {
+            if writer.join().is_err() {
+                error!("Audit log writer panicked");
+            }
+        }
+    }
+}
+
+/// Writes every line sent to the log until the sender is dropped, syncing the file to disk after
+/// every batch of lines
+fn write_lines(mut writer: Writer, lines: Receiver<Vec<u8>>) {
+    while let Ok(line) = lines.recv() {
+        let batch = std::iter::once(line).chain(lines.try_iter());
+        let result = batch
+            .map(|line| writer.write(&line))
+            .fold(Ok(()), Result::and)
+            .and_then(|()| writer.sync());
+        if let Err(e) = result {
+            error!(error = %e, path = %writer.path.display(), "Unable to write to audit log");
+        }
+    }
+}
+
+/// Builds the entry for a request
+fn entry(request: &Request<'_>, result: &Result<OperationResponse, RpcError>) -> serde_json::Value {
+    let manifest_hash = request
+        .manifest
+        .map(|manifest| format!("{:x}", Sha256::digest(manifest)));
+    let mut entry = json!({
+        "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "actorId": request.actor_id,
+        "linkName": request.link_name,
+        "operation": request.operation,
+        "group": request.group,
+        "version": request.version,
+        "kind": request.kind,
+        "namespace": request.namespace,
+        "name": request.name,
+        "manifestHash": manifest_hash,
+    });
+    let fields = match result {
+        Ok(resp) => json!({
+            "outcome": outcome(resp),
+            "errorKind": resp.error_kind,
+            "error": resp.error,
+            "policyViolations": resp
+                .policy_violations
+                .iter()
+                .map(|v| json!({"policy": v.policy, "field": v.field, "message": v.message}))
+                .collect::<Vec<_>>(),
+            "clusters": resp
+                .cluster_results
+                .iter()
+                .flatten()
+                .map(|r| {
+                    json!({
+                        "cluster": r.cluster,
+                        "succeeded": r.succeeded,
+                        "created": r.created,
+                        "unchanged": r.unchanged,
+                        "resourceVersion": r.resource_version,
+                        "error": r.error,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => json!({
+            "outcome": "Failed",
+            "error": e.to_string(),
+        }),
+    };
+    if let (Some(entry), serde_json::Value::Object(fields)) = (entry.as_object_mut(), fields) {
+        entry.extend(fields);
+    }
+    entry
+}
+
+/// Summarizes the response as `Succeeded`, `Unchanged` (every cluster skipped the apply), `Denied`
+/// (the provider's checks rejected the request) or `Failed`
+pub fn outcome(resp: &OperationResponse) -> &'static str {
+    if resp.succeeded {
+        let unchanged = resp
+            .cluster_results
+            .as_ref()
+            .map(|results| !results.is_empty() && results.iter().all(|r| r.unchanged))
+            .unwrap_or_default();
+        return if unchanged { "Unchanged" } else { "Succeeded" };
+    }
+    let denied = [ErrorKind::PolicyDenied, ErrorKind::Untrusted]
+        .iter()
+        .any(|kind| resp.error_kind.as_deref() == Some(kind.as_str()));
+    if denied {
+        "Denied"
+    } else {
+        "Failed"
+    }
+}
+
+struct Writer {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    max_files: u32,
+}
+
+impl Writer {
+    fn open(settings: &AuditSettings) -> io::Result<Writer> {
+        let path = PathBuf::from(&settings.path);
+        let file = open_file(&path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+        Ok(Writer {
+            path,
+            file,
+            size,
+            max_bytes: settings.max_bytes,
+            max_files: settings.max_files,
+        })
+    }
+
+    fn write(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.sync_data()
+    }
+
+    /// Moves every file up by one (the current file becoming `.1`), dropping the oldest
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+        self.sync()?;
+        for i in (1..self.max_files).rev() {
+            let from = self.rotated(i);
+            if from.exists() {
+                std::fs::rename(from, self.rotated(i + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated(1))?;
+        self.file = open_file(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn rotated(&self, n: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+}
+
+fn open_file(path: &PathBuf) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    // The log shows who changed what, so only the provider's user may read it
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+#[cfg(test)]
+mod test {
+    use kubernetes_applier_interface::{ClusterResult, PolicyViolation};
+
+    use super::*;
+
+    fn request(manifest: Option<&[u8]>) -> Request<'_> {
+        Request {
+            operation: "Apply",
+            actor_id: "MACTOR",
+            link_name: Some("default"),
+            group: "apps",
+            version: "v1",
+            kind: "Deployment",
+            namespace: Some("default"),
+            name: "web",
+            manifest,
+        }
+    }
+
+    #[test]
+    fn entries() {
+        let resp = OperationResponse {
+            succeeded: true,
+            cluster_results: Some(vec![ClusterResult {
+                cluster: "default".to_owned(),
+                succeeded: true,
+                created: true,
+                resource_version: Some("42".to_owned()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let written = entry(&request(Some(b"kind: Deployment")), &Ok(resp));
+        assert_eq!(written["outcome"], "Succeeded");
+        assert_eq!(written["actorId"], "MACTOR");
+        assert_eq!(written["clusters"][0]["resourceVersion"], "42");
+        assert_eq!(written["manifestHash"].as_str().unwrap().len(), 64);
+
+        let denied = OperationResponse {
+            succeeded: false,
+            error_kind: Some(ErrorKind::PolicyDenied.into()),
+            policy_violations: vec![PolicyViolation {
+                policy: "data.applier.deny".to_owned(),
+                field: String::new(),
+                message: "No".to_owned(),
+            }],
+            ..Default::default()
+        };
+        let written = entry(&request(None), &Ok(denied));
+        assert_eq!(written["outcome"], "Denied");
+        assert_eq!(written["policyViolations"][0]["message"], "No");
+        assert!(written["manifestHash"].is_null());
+
+        let written = entry(
+            &request(None),
+            &Err(RpcError::InvalidParameter("bad".to_owned())),
+        );
+        assert_eq!(written["outcome"], "Failed");
+
+        // Requests rejected before their link was found have no link name
+        let rejected = Request {
+            link_name: None,
+            ..request(None)
+        };
+        let written = entry(
+            &rejected,
+            &Err(RpcError::InvalidParameter("No link".to_owned())),
+        );
+        assert!(written["linkName"].is_null());
+        assert_eq!(written["error"], "invalid parameter: No link");
+    }
+
+    #[test]
+    fn close_drains_queue() {
+        let dir = std::env::temp_dir().join(format!("applier-audit-close-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log");
+        let log = AuditLog::open(&AuditSettings {
+            path: path.to_string_lossy().into_owned(),
+            max_bytes: u64::MAX,
+            max_files: 0,
+        })
+        .unwrap();
+        for _ in 0..100 {
+            log.clone()
+                .record(&request(None), &Ok(OperationResponse::default()));
+        }
+        log.close();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 100);
+        // Entries recorded after closing are dropped rather than blocking or panicking
+        log.record(&request(None), &Ok(OperationResponse::default()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = std::env::temp_dir().join(format!("applier-audit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log");
+        let mut writer = Writer::open(&AuditSettings {
+            path: path.to_string_lossy().into_owned(),
+            max_bytes: 1,
+            max_files: 2,
+        })
+        .unwrap();
+        for _ in 0..4 {
+            writer.write(b"{}\n").unwrap();
+        }
+        // Every entry is over the limit, so each one ends up in its own file and the oldest is gone
+        let lines = |p: &std::path::Path| std::fs::read_to_string(p).unwrap().lines().count();
+        assert_eq!(lines(&path), 1);
+        assert_eq!(lines(&dir.join("audit.log.1")), 1);
+        assert_eq!(lines(&dir.join("audit.log.2")), 1);
+        assert!(!dir.join("audit.log.3").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
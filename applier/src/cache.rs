@@ -0,0 +1,104 @@
+//! A cache of cluster clients so links with identical configuration share a single client
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use http::Uri;
+use tracing::{debug, trace};
+use wasmbus_rpc::error::RpcError;
+
+use crate::{
+    cluster::{ClusterClient, WeakClusterClient},
+    config::{
+        ensure_tls_allowed, load_config, ClientOptions, ClusterSettings, ConfigSource,
+        TransportSettings,
+    },
+    reload::watch_kubeconfig,
+};
+
+/// Everything that goes into building a client. The cluster name is left out on purpose as it is
+/// just a label chosen by each link
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    source: ConfigSource,
+    server_urls: Vec<Uri>,
+    transport: TransportSettings,
+    options: ClientOptions,
+}
+
+/// Shares clients (and their connection pools) between links with the same effective config. The
+/// cache only holds weak references, so a client is dropped as soon as the last link using it is
+/// removed
+#[derive(Clone, Default)]
+pub struct ClientCache {
+    clients: Arc<Mutex<HashMap<ClientKey, WeakClusterClient>>>,
+    /// Whether configs that disable TLS verification may be used
+    allow_insecure_tls: bool,
+}
+
+impl ClientCache {
+    pub fn new(allow_insecure_tls: bool) -> ClientCache {
+        ClientCache {
+            clients: Default::default(),
+            allow_insecure_tls,
+        }
+    }
+
+    /// Returns the cached client for the given settings or connects a new one if none exists
+    pub async fn get_or_connect(
+        &self,
+        settings: &ClusterSettings,
+        options: &ClientOptions,
+    ) -> Result<ClusterClient, RpcError> {
+        let key = ClientKey {
+            source: settings.source.clone(),
+            server_urls: settings.server_urls.clone(),
+            transport: settings.transport.clone(),
+            options: options.clone(),
+        };
+        if let Some(client) = self.get(&key) {
+            debug!(cluster = %settings.name, "Reusing existing client with identical config");
+            return Ok(client);
+        }
+
+        // Attempt to load the config. If nothing it passed attempt to infer it from the pod or
+        // the default kubeconfig path
+        let config = load_config(&settings.source).await?;
+        ensure_tls_allowed(&config, &settings.name, self.allow_insecure_tls)?;
+        let client = ClusterClient::connect(settings, config, options).await?;
+
+        // We don't hold the lock while connecting as that can take a while. If another link
+        // connected with the same config in the meantime, we use theirs and drop ours
+        // SAFETY: The lock is never held across an await or in code that can panic
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(existing) = clients.get(&key).and_then(WeakClusterClient::upgrade) {
+            return Ok(existing);
+        }
+        watch_kubeconfig(
+            &client,
+            settings.clone(),
+            options.clone(),
+            self.allow_insecure_tls,
+        );
+        clients.insert(key, client.downgrade());
+        Ok(client)
+    }
+
+    /// Returns the number of clients that are still used by at least one link
+    pub fn len(&self) -> usize {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        let clients = self.clients.lock().unwrap();
+        clients.values().filter(|c| c.upgrade().is_some()).count()
+    }
+
+    fn get(&self, key: &ClientKey) -> Option<ClusterClient> {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        let mut clients = self.clients.lock().unwrap();
+        // Clean up any clients that are no longer used by a link while we have the lock
+        clients.retain(|_, c| c.upgrade().is_some());
+        trace!(cached = clients.len(), "Looking up client in cache");
+        clients.get(key).and_then(WeakClusterClient::upgrade)
+    }
+}
@@ -0,0 +1,379 @@
+//! A client for a single cluster that can fail over between multiple API server endpoints
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, RwLock, Weak,
+    },
+    time::Duration,
+};
+
+use http::{HeaderValue, Request, Uri};
+use hyper_rustls::HttpsConnector;
+use hyper_timeout::TimeoutConnector;
+use kube::{
+    client::{ClientBuilder, ConfigExt},
+    Client, Config,
+};
+use tower::{util::MapRequestLayer, ServiceBuilder};
+use tower_http::trace::TraceLayer;
+use tracing::{debug, info, instrument, trace, warn};
+use wasmbus_rpc::error::RpcError;
+
+use crate::{
+    config::{ClientOptions, ClusterSettings, TransportSettings},
+    connector::{Connector, Proxy},
+    metrics, telemetry, warnings,
+};
+
+/// How often endpoints that have failed are checked to see if they have recovered
+const REPROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+struct Endpoint {
+    url: Uri,
+    client: Client,
+    healthy: AtomicBool,
+}
+
+struct Inner {
+    name: String,
+    endpoints: Vec<Endpoint>,
+    /// The index of the endpoint currently in use
+    active: AtomicUsize,
+    /// Whether TLS certificate verification is disabled for this cluster
+    insecure: bool,
+    default_namespace: String,
+}
+
+/// A cluster client that sends requests to the active API server endpoint and fails over to the
+/// next healthy one on connection errors. Cloning is cheap and all clones share endpoint state
+#[derive(Clone)]
+pub struct ClusterClient {
+    // The endpoints are behind a lock so they can be swapped out when the config changes. Requests
+    // that are in flight keep using the endpoints they started with
+    inner: Arc<RwLock<Arc<Inner>>>,
+}
+
+/// A handle to a [`ClusterClient`] that does not keep it alive
+pub struct WeakClusterClient {
+    inner: Weak<RwLock<Arc<Inner>>>,
+}
+
+impl WeakClusterClient {
+    pub fn upgrade(&self) -> Option<ClusterClient> {
+        self.inner.upgrade().map(|inner| ClusterClient { inner })
+    }
+}
+
+impl ClusterClient {
+    /// Creates a client for each of the server URLs in the settings (or the server in the config if
+    /// none are given) and checks which of them are reachable. At least one endpoint must be
+    /// reachable
+    #[instrument(level = "debug", skip_all, fields(cluster = %settings.name))]
+    pub async fn connect(
+        settings: &ClusterSettings,
+        mut config: Config,
+        options: &ClientOptions,
+    ) -> Result<ClusterClient, RpcError> {
+        let name = settings.name.as_str();
+        settings.transport.apply(&mut config);
+        let urls = if settings.server_urls.is_empty() {
+            vec![config.cluster_url.clone()]
+        } else {
+            settings.server_urls.clone()
+        };
+
+        let mut endpoints = Vec::with_capacity(urls.len());
+        for url in urls {
+            let mut config = config.clone();
+            config.cluster_url = url.clone();
+            tracing::trace!(?config, "Attempting to create client and connect to server");
+            let client = build_client(config, &settings.transport, options).map_err(|e| {
+                RpcError::ProviderInit(format!(
+                    "Unable to create client from loaded kubeconfig for cluster {}: {}",
+                    name, e
+                ))
+            })?;
+            endpoints.push(Endpoint {
+                url,
+                client,
+                healthy: AtomicBool::new(false),
+            });
+        }
+
+        // NOTE: In the future, we may want to improve this with a retry
+        let mut last_err = None;
+        for endpoint in endpoints.iter() {
+            match endpoint.client.apiserver_version().await {
+                Ok(_) => {
+                    trace!(url = %endpoint.url, "Successfully connected to server");
+                    endpoint.healthy.store(true, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    warn!(url = %endpoint.url, error = %e, "Unable to connect to API server endpoint");
+                    last_err = Some(e);
+                }
+            }
+        }
+        let active = endpoints
+            .iter()
+            .position(|e| e.healthy.load(Ordering::Relaxed))
+            .ok_or_else(|| {
+                RpcError::ProviderInit(format!(
+                    "Unable to connect to the Kubernetes API server for cluster {}: {}",
+                    name,
+                    last_err.map(|e| e.to_string()).unwrap_or_default()
+                ))
+            })?;
+
+        let inner = Arc::new(Inner {
+            name: name.to_owned(),
+            endpoints,
+            active: AtomicUsize::new(active),
+            insecure: config.accept_invalid_certs,
+            default_namespace: config.default_namespace.clone(),
+        });
+        if inner.endpoints.len() > 1 {
+            tokio::spawn(reprobe(Arc::downgrade(&inner)));
+        }
+
+        Ok(ClusterClient {
+            inner: Arc::new(RwLock::new(inner)),
+        })
+    }
+
+    pub fn downgrade(&self) -> WeakClusterClient {
+        WeakClusterClient {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+
+    /// Atomically replaces the endpoints of this client with those of the given client. All clones
+    /// of this client will use the new endpoints for any new requests
+    pub fn replace_with(&self, other: ClusterClient) {
+        let new = other.current();
+        // SAFETY: The lock is only held for simple reads and writes, so it can't be poisoned
+        *self.inner.write().unwrap() = new;
+    }
+
+    fn current(&self) -> Arc<Inner> {
+        // SAFETY: The lock is only held for simple reads and writes, so it can't be poisoned
+        self.inner.read().unwrap().clone()
+    }
+
+    /// Runs the given operation against the active endpoint. If it fails to connect, the endpoint
+    /// is marked as unhealthy and the operation is retried on the next endpoint, trying healthy
+    /// endpoints first. Any other error is returned as is
+    pub async fn call<F, Fut, T>(&self, op: F) -> Result<T, kube::Error>
+    where
+        F: Fn(Client) -> Fut,
+        Fut: Future<Output = Result<T, kube::Error>>,
+    {
+        let inner = self.current();
+        let mut last_err = None;
+        for idx in inner.candidates() {
+            let endpoint = &inner.endpoints[idx];
+            match op(endpoint.client.clone()).await {
+                Err(e) if is_connection_error(&e) => {
+                    warn!(cluster = %inner.name, url = %endpoint.url, error = %e, "Unable to connect to API server endpoint, failing over");
+                    endpoint.healthy.store(false, Ordering::Relaxed);
+                    metrics::record_failover(&inner.name);
+                    last_err = Some(e);
+                }
+                res => {
+                    if let Err(kube::Error::Api(resp)) = &res {
+                        metrics::record_api_error(&inner.name, resp.code);
+                    }
+                    endpoint.healthy.store(true, Ordering::Relaxed);
+                    if inner.active.swap(idx, Ordering::Relaxed) != idx {
+                        info!(cluster = %inner.name, url = %endpoint.url, "Switched active API server endpoint");
+                    }
+                    return res;
+                }
+            }
+        }
+        // SAFETY: There is always at least one endpoint, so we only get here after an error
+        Err(last_err.unwrap())
+    }
+
+    /// Returns the client of an endpoint that is reachable right now, failing over like
+    /// [`ClusterClient::call`]. Used for long running requests such as watches, which can't be
+    /// retried as a whole
+    pub async fn reachable_client(&self) -> Result<Client, kube::Error> {
+        self.call(|c| async move { c.apiserver_version().await.map(|_| c) })
+            .await
+    }
+
+    /// Returns the namespace used for requests that don't specify one
+    pub fn default_namespace(&self) -> String {
+        self.current().default_namespace.clone()
+    }
+
+    /// Returns the URL of the endpoint currently in use
+    pub fn active_endpoint(&self) -> Uri {
+        let inner = self.current();
+        inner.endpoints[inner.active.load(Ordering::Relaxed)]
+            .url
+            .clone()
+    }
+
+    /// Returns whether this client skips TLS certificate verification
+    pub fn is_insecure(&self) -> bool {
+        self.current().insecure
+    }
+
+    /// Returns the number of healthy endpoints and the total number of endpoints
+    pub fn endpoint_health(&self) -> (usize, usize) {
+        let inner = self.current();
+        let healthy = inner
+            .endpoints
+            .iter()
+            .filter(|e| e.healthy.load(Ordering::Relaxed))
+            .count();
+        (healthy, inner.endpoints.len())
+    }
+}
+
+impl Inner {
+    /// The order endpoints should be tried in: starting at the active endpoint, all healthy
+    /// endpoints followed by all unhealthy ones
+    fn candidates(&self) -> Vec<usize> {
+        let len = self.endpoints.len();
+        let active = self.active.load(Ordering::Relaxed);
+        let (mut healthy, unhealthy): (Vec<usize>, Vec<usize>) = (0..len)
+            .map(|i| (active + i) % len)
+            .partition(|i| self.endpoints[*i].healthy.load(Ordering::Relaxed));
+        healthy.extend(unhealthy);
+        healthy
+    }
+}
+
+/// Creates a client from the config with the additional behavior requested in the transport
+/// settings and options
+fn build_client(
+    config: Config,
+    transport: &TransportSettings,
+    options: &ClientOptions,
+) -> Result<Client, String> {
+    let impersonation = impersonation_layer(options)?;
+    // kube doesn't support proxies, connecting to an address other than the one it verifies or a
+    // separate connect timeout, so we only build the stack ourselves when one of those is needed
+    if config.proxy_url.is_none()
+        && transport.tls_server_name.is_none()
+        && options.connect_timeout.is_none()
+    {
+        let builder = ClientBuilder::try_from(config).map_err(|e| e.to_string())?;
+        return Ok(builder
+            .with_layer(&impersonation)
+            .with_layer(&warnings::layer())
+            .build());
+    }
+
+    let mut config = config;
+    let address = match transport.tls_server_name.as_deref() {
+        Some(server_name) => {
+            let original =
+                config.cluster_url.authority().cloned().ok_or_else(|| {
+                    format!("Server URL {} is missing a host", config.cluster_url)
+                })?;
+            // The cluster URL is what TLS verifies against, so we swap in the server name and have
+            // the connector dial the original address instead
+            let authority = match original.port() {
+                Some(port) => format!("{}:{}", server_name, port),
+                None => server_name.to_owned(),
+            };
+            let mut parts = config.cluster_url.clone().into_parts();
+            parts.authority = Some(authority.parse().map_err(|e| format!("{}", e))?);
+            config.cluster_url = Uri::from_parts(parts).map_err(|e| e.to_string())?;
+            debug!(%server_name, address = %original, "Overriding TLS server name");
+            Some(original)
+        }
+        None => None,
+    };
+    let proxy = config.proxy_url.clone().map(Proxy::try_from).transpose()?;
+    if let Some(proxy) = config.proxy_url.as_ref() {
+        debug!(proxy = %proxy.host().unwrap_or_default(), "Connecting to API server through proxy");
+    }
+
+    let tls = config.rustls_client_config().map_err(|e| e.to_string())?;
+    let https = HttpsConnector::from((Connector::new(proxy, address), Arc::new(tls)));
+    let mut connector = TimeoutConnector::new(https);
+    connector.set_connect_timeout(options.connect_timeout.or(config.timeout));
+    connector.set_read_timeout(config.timeout);
+    let client = hyper::Client::builder().build(connector);
+
+    let service = ServiceBuilder::new()
+        .layer(config.base_uri_layer())
+        .option_layer(config.auth_layer().map_err(|e| e.to_string())?)
+        .layer(config.extra_headers_layer().map_err(|e| e.to_string())?)
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::http_span)
+                .on_response(telemetry::record_response),
+        )
+        .service(client);
+    Ok(ClientBuilder::new(service, config.default_namespace)
+        .with_layer(&impersonation)
+        .with_layer(&warnings::layer())
+        .build())
+}
+
+/// Returns a layer adding the impersonation headers requested in the options to every request.
+/// The layer does nothing if impersonation is disabled
+fn impersonation_layer(
+    options: &ClientOptions,
+) -> Result<MapRequestLayer<impl Fn(Request<hyper::Body>) -> Request<hyper::Body> + Clone>, String>
+{
+    let mut headers = Vec::new();
+    if let Some(impersonation) = options.impersonation.as_ref() {
+        let user = HeaderValue::from_str(&impersonation.user)
+            .map_err(|e| format!("Invalid user to impersonate: {}", e))?;
+        headers.push(("Impersonate-User", user));
+        for group in impersonation.groups.iter() {
+            let group = HeaderValue::from_str(group)
+                .map_err(|e| format!("Invalid group to impersonate: {}", e))?;
+            headers.push(("Impersonate-Group", group));
+        }
+        debug!(user = %impersonation.user, groups = ?impersonation.groups, "Impersonating user for all requests");
+    }
+    Ok(MapRequestLayer::new(
+        move |mut req: Request<hyper::Body>| {
+            for (name, value) in headers.iter() {
+                req.headers_mut().append(*name, value.clone());
+            }
+            req
+        },
+    ))
+}
+
+/// Only errors where we never managed to connect are retried, as we can't know if any other failed
+/// request was processed by the server
+fn is_connection_error(e: &kube::Error) -> bool {
+    matches!(e, kube::Error::HyperError(e) if e.is_connect())
+}
+
+/// Periodically checks unhealthy endpoints and marks them as healthy again once they respond.
+/// Stops once the client has been dropped
+async fn reprobe(inner: Weak<Inner>) {
+    let mut ticker = tokio::time::interval(REPROBE_INTERVAL);
+    // The first tick completes immediately
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let inner = match inner.upgrade() {
+            Some(i) => i,
+            None => return,
+        };
+        for endpoint in inner
+            .endpoints
+            .iter()
+            .filter(|e| !e.healthy.load(Ordering::Relaxed))
+        {
+            if endpoint.client.apiserver_version().await.is_ok() {
+                debug!(cluster = %inner.name, url = %endpoint.url, "API server endpoint recovered");
+                endpoint.healthy.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Machine readable error kinds returned to actors in `errorKind`
+
+use std::fmt;
+
+use kubernetes_applier_interface::{GetResponse, ListResponse, OperationResponse};
+
+/// The kinds of errors actors may want to handle specifically. These must match the values of the
+/// `ErrorKind` enum in the interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The link exceeded its rate limit or maximum number of requests in flight
+    RateLimited,
+    /// The request didn't finish within its timeout
+    Timeout,
+    /// The API server returned warnings and the link treats warnings as errors
+    Warnings,
+    /// The object was rejected by strict field validation
+    Invalid,
+    /// The object was rejected by the link's policy checks
+    PolicyDenied,
+    /// The manifest wasn't signed by a signer the link trusts
+    Untrusted,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::RateLimited => "RateLimited",
+            ErrorKind::Timeout => "Timeout",
+            ErrorKind::Warnings => "Warnings",
+            ErrorKind::Invalid => "Invalid",
+            ErrorKind::PolicyDenied => "PolicyDenied",
+            ErrorKind::Untrusted => "Untrusted",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<ErrorKind> for String {
+    fn from(kind: ErrorKind) -> String {
+        kind.as_str().to_owned()
+    }
+}
+
+/// An error with a kind that is returned to the actor in the response rather than as an RPC error
+#[derive(Debug, Clone)]
+pub struct RequestError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl RequestError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> RequestError {
+        RequestError {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<RequestError> for OperationResponse {
+    fn from(e: RequestError) -> OperationResponse {
+        OperationResponse {
+            succeeded: false,
+            error: Some(e.message),
+            error_kind: Some(e.kind.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<RequestError> for GetResponse {
+    fn from(e: RequestError) -> GetResponse {
+        GetResponse {
+            succeeded: false,
+            error: Some(e.message),
+            error_kind: Some(e.kind.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<RequestError> for ListResponse {
+    fn from(e: RequestError) -> ListResponse {
+        ListResponse {
+            succeeded: false,
+            error: Some(e.message),
+            error_kind: Some(e.kind.into()),
+            ..Default::default()
+        }
+    }
+}
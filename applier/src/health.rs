@@ -0,0 +1,195 @@
+//! Periodic checks that every cluster of a link can be reached and still accepts the link's
+//! credentials, so the provider's health check can report links that will fail their requests
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::future::join_all;
+use http::Request;
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::cluster::ClusterClient;
+
+/// How often each cluster is probed
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a single probe may take before the cluster is considered unreachable
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// The versions of the SelfSubjectReview API to try, newest first. It is GA as of Kubernetes 1.28
+const REVIEW_VERSIONS: &[&str] = &["v1", "v1beta1"];
+
+/// The result of the last probe of each cluster. Clusters that haven't been probed yet are missing
+type Results = Arc<Mutex<BTreeMap<String, Result<(), String>>>>;
+
+struct Inner {
+    results: Results,
+    task: JoinHandle<()>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Probes every cluster of a link in the background. Cloning is cheap and probing stops once the
+/// last clone is dropped
+#[derive(Clone)]
+pub struct LinkHealth {
+    inner: Arc<Inner>,
+}
+
+impl LinkHealth {
+    /// Starts probing the given clusters, the first probe being sent right away
+    pub fn start(clusters: &HashMap<String, ClusterClient>) -> LinkHealth {
+        let results = Results::default();
+        let clusters: Vec<(String, ClusterClient)> = clusters
+            .iter()
+            .map(|(name, client)| (name.clone(), client.clone()))
+            .collect();
+        LinkHealth {
+            inner: Arc::new(Inner {
+                results: results.clone(),
+                task: tokio::spawn(probe_all(clusters, results)),
+            }),
+        }
+    }
+
+    /// Returns the clusters that failed their last probe with the reason, sorted by cluster
+    pub fn failures(&self) -> Vec<(String, String)> {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        let results = self.inner.results.lock().unwrap();
+        results
+            .iter()
+            .filter_map(|(cluster, result)| {
+                result.as_ref().err().map(|e| (cluster.clone(), e.clone()))
+            })
+            .collect()
+    }
+}
+
+async fn probe_all(clusters: Vec<(String, ClusterClient)>, results: Results) {
+    let mut ticker = tokio::time::interval(PROBE_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let probes = join_all(clusters.iter().map(|(name, client)| async move {
+            let result = match tokio::time::timeout(PROBE_TIMEOUT, probe(client)).await {
+                Ok(result) => result,
+                Err(_) => Err(format!(
+                    "API server did not respond within {}s",
+                    PROBE_TIMEOUT.as_secs()
+                )),
+            };
+            if let Err(e) = result.as_ref() {
+                warn!(cluster = %name, error = %e, "Cluster failed health probe");
+            }
+            (name.clone(), result)
+        }))
+        .await;
+        // SAFETY: The lock is never held across an await or in code that can panic
+        results.lock().unwrap().extend(probes);
+    }
+}
+
+/// Checks that the API server responds and accepts the client's credentials
+async fn probe(client: &ClusterClient) -> Result<(), String> {
+    let version = client
+        .call(|c| async move { c.apiserver_version().await })
+        .await
+        .map_err(|e| format!("Unable to reach API server: {}", e))?;
+    // The version is usually readable without credentials, so we also ask who we are
+    let user = review(client).await?;
+    debug!(version = %version.git_version, ?user, "Cluster passed health probe");
+    Ok(())
+}
+
+/// Sends a SelfSubjectReview, returning the username the API server authenticated the client as.
+/// Clusters that don't serve the API or don't allow the review still accepted the credentials,
+/// so only other errors (most importantly 401 Unauthorized) are returned
+async fn review(client: &ClusterClient) -> Result<Option<String>, String> {
+    for version in REVIEW_VERSIONS {
+        let resp = client
+            .call(|c| async move { c.request::<Value>(review_request(version)).await })
+            .await;
+        match resp {
+            Ok(review) => return Ok(username(&review)),
+            Err(kube::Error::Api(e)) if e.code == 404 => continue,
+            Err(kube::Error::Api(e)) if e.code == 403 => return Ok(None),
+            Err(kube::Error::Api(e)) if e.code == 401 => {
+                return Err(format!("API server rejected credentials: {}", e.message))
+            }
+            Err(e) => return Err(format!("Unable to review credentials: {}", e)),
+        }
+    }
+    Ok(None)
+}
+
+fn review_request(version: &str) -> Request<Vec<u8>> {
+    let body = json!({
+        "apiVersion": format!("authentication.k8s.io/{}", version),
+        "kind": "SelfSubjectReview",
+    });
+    Request::post(format!(
+        "/apis/authentication.k8s.io/{}/selfsubjectreviews",
+        version
+    ))
+    .header("content-type", "application/json")
+    // SAFETY: The request is built from valid parts and a JSON value is always serializable
+    .body(serde_json::to_vec(&body).unwrap())
+    .unwrap()
+}
+
+fn username(review: &Value) -> Option<String> {
+    review
+        .pointer("/status/userInfo/username")
+        .and_then(Value::as_str)
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn review_requests() {
+        let req = review_request("v1");
+        assert_eq!(
+            req.uri(),
+            "/apis/authentication.k8s.io/v1/selfsubjectreviews"
+        );
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["apiVersion"], "authentication.k8s.io/v1");
+
+        let review = json!({
+            "apiVersion": "authentication.k8s.io/v1",
+            "kind": "SelfSubjectReview",
+            "status": {"userInfo": {"username": "system:serviceaccount:default:applier"}},
+        });
+        assert_eq!(
+            username(&review).as_deref(),
+            Some("system:serviceaccount:default:applier")
+        );
+        assert_eq!(username(&json!({})), None);
+    }
+
+    #[tokio::test]
+    async fn failures() {
+        let health = LinkHealth::start(&HashMap::new());
+        assert!(health.failures().is_empty());
+        health.inner.results.lock().unwrap().extend([
+            (
+                "prod".to_owned(),
+                Err("Unable to reach API server".to_owned()),
+            ),
+            ("dev".to_owned(), Ok(())),
+        ]);
+        assert_eq!(
+            health.failures(),
+            vec![("prod".to_owned(), "Unable to reach API server".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Parsing of container image references and the rewriting of images to registry mirrors, optionally
+//! pinning them to the digests in a lock map
+
+use std::{collections::HashMap, fmt};
+
+use serde_json::Value;
+use wasmbus_rpc::error::RpcError;
+
+use crate::{
+    admission,
+    config::{self, key_values},
+};
+
+/// Rules rewriting container images to registry mirrors, as a comma delimited list of
+/// `prefix->replacement` pairs, e.g. `docker.io/->mirror.local/dockerhub/`
+const IMAGE_REWRITES_KEY: &str = "image_rewrites";
+/// Digests images are pinned to, as a comma delimited list of `image:tag=sha256:<hash>` pairs
+const IMAGE_DIGESTS_KEY: &str = "image_digests";
+/// The path to a JSON object of `image:tag` to digest, used in the same way as `image_digests`
+const IMAGE_DIGESTS_FILE_KEY: &str = "image_digests_file";
+
+/// The registry images without one are pulled from
+const DEFAULT_REGISTRY: &str = "docker.io";
+/// The tag used for images without a tag or digest
+const DEFAULT_TAG: &str = "latest";
+/// The fields containers with rewritten images are listed in
+const CONTAINER_FIELDS: &[&str] = &["initContainers", "containers"];
+
+/// Settings for rewriting the images of containers before they are applied
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageSettings {
+    /// Prefixes of the full image repository and what they are replaced with, in the order they are
+    /// tried
+    pub rewrites: Vec<(String, String)>,
+    pub digests: Vec<(String, String)>,
+    pub digests_file: Option<String>,
+}
+
+impl ImageSettings {
+    /// Parses the image settings from the given (lowercased) link values. Returns `None` if images
+    /// aren't rewritten
+    pub fn from_values(
+        values: &HashMap<String, String>,
+    ) -> Result<Option<ImageSettings>, RpcError> {
+        let rewrites = values
+            .get(IMAGE_REWRITES_KEY)
+            .map(|raw| {
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|rule| match rule.split_once("->") {
+                        Some((from, to)) if !from.trim().is_empty() => {
+                            Ok((from.trim().to_owned(), to.trim().to_owned()))
+                        }
+                        _ => Err(RpcError::ProviderInit(format!(
+                            "Invalid rule {} in {}, must be in the form prefix->replacement",
+                            rule, IMAGE_REWRITES_KEY
+                        ))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let settings = ImageSettings {
+            rewrites,
+            digests: key_values(values, IMAGE_DIGESTS_KEY)?,
+            digests_file: values
+                .get(IMAGE_DIGESTS_FILE_KEY)
+                .map(|path| path.trim().to_owned()),
+        };
+        if settings == ImageSettings::default() {
+            return Ok(None);
+        }
+        Ok(Some(settings))
+    }
+}
+
+/// A parsed image reference, with the registry filled in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// The full repository including the registry, e.g. `docker.io/library/nginx`
+    pub repository: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
+}
+
+impl Reference {
+    /// Parses an image reference such as `nginx:1.23` or `ghcr.io/cosmonic/app@sha256:<hash>`
+    pub fn parse(image: &str) -> Reference {
+        let (name, digest) = match image.split_once('@') {
+            Some((name, digest)) => (name, Some(digest.to_owned())),
+            None => (image, None),
+        };
+        // A tag is a colon after the last slash, anything before that is a registry port
+        let (name, tag) = match name.rsplit_once(':') {
+            Some((repo, tag)) if !tag.contains('/') => (repo, Some(tag.to_owned())),
+            _ => (name, None),
+        };
+        let repository = match name.split_once('/') {
+            Some((first, _))
+                if first.contains('.') || first.contains(':') || first == "localhost" =>
+            {
+                name.to_owned()
+            }
+            Some(_) => format!("{}/{}", DEFAULT_REGISTRY, name),
+            None => format!("{}/library/{}", DEFAULT_REGISTRY, name),
+        };
+        Reference {
+            repository,
+            tag,
+            digest,
+        }
+    }
+
+    /// Returns the repository and tag the image is locked by, using `latest` if there is no tag
+    fn lock_key(&self) -> String {
+        format!(
+            "{}:{}",
+            self.repository,
+            self.tag.as_deref().unwrap_or(DEFAULT_TAG)
+        )
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the full repository of the image with the registry, e.g. `nginx:1.23` becomes
+/// `docker.io/library/nginx`
+pub fn normalize(image: &str) -> String {
+    Reference::parse(image).repository
+}
+
+/// Rewrites images according to a link's rewrite rules and digest lock
+#[derive(Debug, Clone)]
+pub struct ImageRewriter {
+    rewrites: Vec<(String, String)>,
+    /// The digest of each locked `repository:tag`
+    digests: HashMap<String, String>,
+}
+
+impl ImageRewriter {
+    /// Creates a rewriter from the given settings, loading the lock file if one is set
+    pub async fn load(settings: ImageSettings) -> Result<ImageRewriter, RpcError> {
+        config::run_blocking("image lock", move || {
+            ImageRewriter::load_blocking(&settings)
+        })
+        .await
+    }
+
+    fn load_blocking(settings: &ImageSettings) -> Result<ImageRewriter, RpcError> {
+        let mut locked = settings.digests.clone();
+        if let Some(path) = settings.digests_file.as_deref() {
+            let raw = std::fs::read_to_string(path).map_err(|e| {
+                RpcError::ProviderInit(format!("Unable to read image lock {}: {}", path, e))
+            })?;
+            let map: HashMap<String, String> = serde_json::from_str(&raw).map_err(|e| {
+                RpcError::ProviderInit(format!(
+                    "Image lock {} must be a JSON object of images to digests: {}",
+                    path, e
+                ))
+            })?;
+            locked.extend(map);
+        }
+        let digests = locked
+            .into_iter()
+            .map(|(image, digest)| {
+                if !digest.contains(':') {
+                    return Err(RpcError::ProviderInit(format!(
+                        "Invalid digest {} for image {}, must be in the form sha256:<hash>",
+                        digest, image
+                    )));
+                }
+                Ok((Reference::parse(&image).lock_key(), digest))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ImageRewriter {
+            rewrites: settings.rewrites.clone(),
+            digests,
+        })
+    }
+
+    /// Returns the image to use instead of the given one, if it matches a rewrite rule or is locked
+    pub fn rewrite(&self, image: &str) -> Option<String> {
+        let mut reference = Reference::parse(image);
+        // The lock is keyed by the image actors ask for, not the mirror it is pulled from
+        let digest = match reference.digest {
+            Some(_) => None,
+            None => self.digests.get(&reference.lock_key()).cloned(),
+        };
+        let rule = self
+            .rewrites
+            .iter()
+            .find(|(from, _)| reference.repository.starts_with(from.as_str()));
+        if digest.is_none() && rule.is_none() {
+            return None;
+        }
+        if let Some((from, to)) = rule {
+            reference.repository = format!("{}{}", to, &reference.repository[from.len()..]);
+        }
+        if digest.is_some() {
+            reference.digest = digest;
+        }
+        let rewritten = reference.to_string();
+        (rewritten != image).then_some(rewritten)
+    }
+
+    /// Rewrites the images of every container and init container in the object's pod spec, returning
+    /// the paths of the images that were changed
+    pub fn rewrite_object(&self, object: &mut Value) -> Vec<String> {
+        let path = match admission::pod_spec_path(object) {
+            Some(path) => path,
+            None => return Vec::new(),
+        };
+        let spec = match object.pointer_mut(&format!("/{}", path.replace('.', "/"))) {
+            Some(spec) => spec,
+            None => return Vec::new(),
+        };
+        let mut rewritten = Vec::new();
+        for list in CONTAINER_FIELDS {
+            let containers = match spec.get_mut(list).and_then(Value::as_array_mut) {
+                Some(containers) => containers,
+                None => continue,
+            };
+            for (i, container) in containers.iter_mut().enumerate() {
+                let image = match container.get_mut("image") {
+                    Some(Value::String(image)) => image,
+                    _ => continue,
+                };
+                if let Some(new_image) = self.rewrite(image) {
+                    *image = new_image;
+                    rewritten.push(format!("{}.{}[{}].image", path, list, i));
+                }
+            }
+        }
+        rewritten
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::test::values;
+
+    use super::*;
+
+    const DIGEST: &str = "sha256:0d17b565c37bcbd895e9d92315a05c1c3c9a29f762b011a10c54a66cd53c9b31";
+
+    fn rewriter() -> ImageRewriter {
+        ImageRewriter::load_blocking(&ImageSettings {
+            rewrites: vec![
+                (
+                    "docker.io/".to_owned(),
+                    "mirror.local/dockerhub/".to_owned(),
+                ),
+                ("ghcr.io/".to_owned(), "mirror.local/ghcr/".to_owned()),
+            ],
+            digests: vec![("nginx:1.23".to_owned(), DIGEST.to_owned())],
+            digests_file: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn normalize_images() {
+        assert_eq!(normalize("nginx"), "docker.io/library/nginx");
+        assert_eq!(normalize("nginx:1.23"), "docker.io/library/nginx");
+        assert_eq!(normalize("bitnami/redis"), "docker.io/bitnami/redis");
+        assert_eq!(normalize("localhost:5000/app:latest"), "localhost:5000/app");
+        assert_eq!(
+            normalize("ghcr.io/cosmonic/app@sha256:abcd"),
+            "ghcr.io/cosmonic/app"
+        );
+        assert_eq!(
+            Reference::parse("localhost:5000/app:1.0@sha256:abcd").to_string(),
+            "localhost:5000/app:1.0@sha256:abcd"
+        );
+    }
+
+    #[test]
+    fn rewrite_images() {
+        let rewriter = rewriter();
+        assert_eq!(
+            rewriter.rewrite("nginx:1.23").unwrap(),
+            format!("mirror.local/dockerhub/library/nginx:1.23@{}", DIGEST)
+        );
+        assert_eq!(
+            rewriter.rewrite("ghcr.io/cosmonic/app:0.1").unwrap(),
+            "mirror.local/ghcr/cosmonic/app:0.1"
+        );
+        // Images that are already pinned keep their digest
+        assert_eq!(
+            rewriter
+                .rewrite("docker.io/library/nginx:1.23@sha256:abcd")
+                .unwrap(),
+            "mirror.local/dockerhub/library/nginx:1.23@sha256:abcd"
+        );
+        assert_eq!(rewriter.rewrite("quay.io/prometheus/prometheus"), None);
+
+        let mut object = serde_json::json!({
+            "kind": "Pod",
+            "spec": {
+                "initContainers": [{"name": "init", "image": "quay.io/init"}],
+                "containers": [{"name": "web", "image": "nginx:1.23"}],
+            }
+        });
+        assert_eq!(
+            rewriter.rewrite_object(&mut object),
+            vec!["spec.containers[0].image"]
+        );
+        assert_eq!(object["spec"]["initContainers"][0]["image"], "quay.io/init");
+    }
+
+    #[test]
+    fn invalid_lock() {
+        assert!(ImageRewriter::load_blocking(&ImageSettings {
+            rewrites: Vec::new(),
+            digests: vec![("nginx:1.23".to_owned(), "abcd".to_owned())],
+            digests_file: None,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn settings() {
+        assert!(ImageSettings::from_values(&HashMap::new())
+            .unwrap()
+            .is_none());
+        let settings = ImageSettings::from_values(&values(&[
+            (
+                "image_rewrites",
+                "docker.io/ -> mirror.local/dockerhub/,ghcr.io/->mirror.local/ghcr/",
+            ),
+            ("image_digests", "nginx:1.23=sha256:abcd"),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            settings.rewrites[0],
+            (
+                "docker.io/".to_owned(),
+                "mirror.local/dockerhub/".to_owned()
+            )
+        );
+        assert_eq!(
+            settings.digests,
+            vec![("nginx:1.23".to_owned(), "sha256:abcd".to_owned())]
+        );
+        assert!(ImageSettings::from_values(&values(&[("image_rewrites", "docker.io/")])).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Per link rate limits and caps on the number of requests in flight, so one chatty actor can't
+//! flood the API server for everyone else
+
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::trace;
+
+use crate::{
+    config::{LimitMode, LimitSettings},
+    error::{ErrorKind, RequestError},
+};
+
+/// A token bucket that refills at a constant rate up to its capacity
+struct TokenBucket {
+    /// Tokens added per second
+    rate: f64,
+    capacity: f64,
+    /// The tokens currently available. This goes negative when requests are queued waiting for
+    /// tokens, which reserves the tokens for them in the order they arrived
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, capacity: u32) -> TokenBucket {
+        TokenBucket {
+            rate,
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token, returning how long the caller must wait before using it. Returns `None` (and
+    /// takes nothing) if the wait would be longer than `max_wait`
+    fn take(&mut self, max_wait: Duration) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+
+        let wait = if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        };
+        if wait > max_wait {
+            return None;
+        }
+        self.tokens -= 1.0;
+        Some(wait)
+    }
+}
+
+/// The point in time a request has to be done by
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    at: tokio::time::Instant,
+    timeout: Duration,
+}
+
+impl Deadline {
+    /// Returns a deadline the given timeout from now
+    pub fn after(timeout: Duration) -> Deadline {
+        Deadline {
+            at: tokio::time::Instant::now() + timeout,
+            timeout,
+        }
+    }
+
+    /// Runs the future until it completes or the deadline (if any) passes
+    pub async fn run<F: Future>(
+        deadline: Option<Deadline>,
+        fut: F,
+    ) -> Result<F::Output, RequestError> {
+        match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.at, fut)
+                .await
+                .map_err(|_| {
+                    RequestError::new(
+                        ErrorKind::Timeout,
+                        format!("Request timed out after {:?}", deadline.timeout),
+                    )
+                }),
+            None => Ok(fut.await),
+        }
+    }
+}
+
+/// Held for as long as a request is in flight
+pub struct Permit {
+    _in_flight: Option<OwnedSemaphorePermit>,
+}
+
+/// Enforces the limits of a single link. Requests either wait for their turn (up to the queue
+/// timeout) or are rejected right away, depending on the configured mode
+pub struct RateLimiter {
+    bucket: Option<Mutex<TokenBucket>>,
+    in_flight: Option<Arc<Semaphore>>,
+    mode: LimitMode,
+    queue_timeout: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(settings: &LimitSettings) -> RateLimiter {
+        RateLimiter {
+            bucket: settings
+                .rate
+                .map(|rate| Mutex::new(TokenBucket::new(rate, settings.burst))),
+            in_flight: settings
+                .max_in_flight
+                .map(|max| Arc::new(Semaphore::new(max))),
+            mode: settings.mode,
+            queue_timeout: settings.queue_timeout,
+        }
+    }
+
+    /// Waits until the request is allowed to go ahead. The returned permit must be held until the
+    /// request is done
+    pub async fn acquire(&self) -> Result<Permit, RequestError> {
+        let deadline = Instant::now() + self.queue_timeout;
+
+        let in_flight = match self.in_flight.clone() {
+            Some(semaphore) => Some(match self.mode {
+                LimitMode::FailFast => semaphore.try_acquire_owned().map_err(|_| {
+                    RequestError::new(
+                        ErrorKind::RateLimited,
+                        "Too many requests in flight for link",
+                    )
+                })?,
+                LimitMode::Queue => {
+                    tokio::time::timeout_at(deadline.into(), semaphore.acquire_owned())
+                        .await
+                        .map_err(|_| {
+                            RequestError::new(
+                                ErrorKind::RateLimited,
+                                format!(
+                                    "Timed out after {:?} waiting for other requests in flight for link to finish",
+                                    self.queue_timeout
+                                ),
+                            )
+                        })?
+                        // SAFETY: We never close the semaphore
+                        .unwrap()
+                }
+            }),
+            None => None,
+        };
+
+        if let Some(bucket) = self.bucket.as_ref() {
+            let max_wait = match self.mode {
+                LimitMode::FailFast => Duration::ZERO,
+                LimitMode::Queue => deadline.saturating_duration_since(Instant::now()),
+            };
+            // SAFETY: The lock is never held across an await or in code that can panic
+            let wait = bucket.lock().unwrap().take(max_wait).ok_or_else(|| {
+                RequestError::new(ErrorKind::RateLimited, "Rate limit exceeded for link")
+            })?;
+            if !wait.is_zero() {
+                trace!(?wait, "Waiting for rate limit");
+                tokio::time::sleep(wait).await;
+            }
+        }
+
+        Ok(Permit {
+            _in_flight: in_flight,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn settings(
+        rate: Option<f64>,
+        max_in_flight: Option<usize>,
+        mode: LimitMode,
+        queue_timeout: Duration,
+    ) -> LimitSettings {
+        LimitSettings {
+            rate,
+            burst: 1,
+            max_in_flight,
+            mode,
+            queue_timeout,
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_fast() {
+        let limiter = RateLimiter::new(&settings(
+            Some(1.0),
+            None,
+            LimitMode::FailFast,
+            Duration::from_secs(10),
+        ));
+        assert!(limiter.acquire().await.is_ok());
+        assert!(limiter.acquire().await.is_err());
+
+        let limiter = RateLimiter::new(&settings(
+            None,
+            Some(1),
+            LimitMode::FailFast,
+            Duration::from_secs(10),
+        ));
+        let permit = limiter.acquire().await.unwrap();
+        assert!(limiter.acquire().await.is_err());
+        drop(permit);
+        assert!(limiter.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn queue() {
+        let limiter = RateLimiter::new(&settings(
+            Some(20.0),
+            None,
+            LimitMode::Queue,
+            Duration::from_secs(1),
+        ));
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await.unwrap();
+        }
+        // The first request uses the burst, the others wait 50ms each
+        assert!(start.elapsed() >= Duration::from_millis(90));
+
+        let limiter = RateLimiter::new(&settings(
+            Some(0.1),
+            Some(1),
+            LimitMode::Queue,
+            Duration::from_millis(20),
+        ));
+        let permit = limiter.acquire().await.unwrap();
+        // Times out waiting for the request in flight
+        assert!(limiter.acquire().await.is_err());
+        drop(permit);
+        // Times out waiting for a token
+        assert!(limiter.acquire().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn deadline() {
+        let deadline = Some(Deadline::after(Duration::from_millis(10)));
+        assert!(Deadline::run(deadline, async {}).await.is_ok());
+        let err = Deadline::run(deadline, tokio::time::sleep(Duration::from_secs(1)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Timeout);
+        assert!(Deadline::run(None, async {}).await.is_ok());
+    }
+}
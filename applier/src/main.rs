@@ -1,30 +1,74 @@
 //! Kubernetes applier capability provider
 //!
 //!
+use futures::future::join_all;
 use kube::{
-    api::{DeleteParams, DynamicObject, PatchParams, PostParams},
-    config::{KubeConfigOptions, Kubeconfig},
+    api::{DeleteParams, DynamicObject, ListParams, PatchParams},
     core::{params::Patch, ApiResource, GroupVersionKind},
-    Api, Client, Config,
+    Api, Client,
 };
 use kubernetes_applier_interface::{
-    DeleteRequest, KubernetesApplier, KubernetesApplierReceiver, OperationResponse,
+    ApplyRequest, ClusterResult, DeleteRequest, GetRequest, GetResponse, KubernetesApplier,
+    KubernetesApplierReceiver, ListRequest, ListResponse, OperationResponse, PolicyViolation,
+    ValidationError,
 };
 use tokio::sync::RwLock;
-use tracing::{debug, info, instrument, trace};
-use wasmbus_rpc::provider::prelude::*;
+use tracing::{debug, error, info, instrument, trace, warn};
+use wasmbus_rpc::{
+    core::{HealthCheckRequest, HealthCheckResponse},
+    provider::prelude::*,
+};
 
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::convert::Infallible;
+use std::sync::{Arc, Once};
+use std::time::{Duration, Instant};
+
+mod admission;
+mod applied;
+mod audit;
+mod cache;
+mod cluster;
+mod config;
+mod connector;
+mod error;
+mod health;
+mod images;
+mod limits;
+mod metrics;
+mod mutation;
+mod policy;
+mod read_cache;
+mod reload;
+mod schema;
+mod secrets;
+mod settings;
+mod signature;
+mod telemetry;
+mod validation;
+mod warnings;
 
-/// Loading a kubeconfig from a file
-const CONFIG_FILE_KEY: &str = "config_file";
-/// Passing a kubeconfig as a base64 encoding string. This config should contain embedded
-/// certificates rather than paths to certificates
-const CONFIG_B64_KEY: &str = "config_b64";
+use admission::AdmissionSettings;
+use applied::{
+    desired_state_hash, AppliedCache, AppliedState, ObjectKey, DESIRED_STATE_ANNOTATION,
+};
+use audit::AuditLog;
+use cache::ClientCache;
+use cluster::ClusterClient;
+use config::{ClientOptions, ClusterSources, LimitSettings, ReadCacheSettings};
+use error::{ErrorKind, RequestError};
+use health::LinkHealth;
+use images::{ImageRewriter, ImageSettings};
+use limits::{Deadline, Permit, RateLimiter};
+use metrics::{CallStats, Metrics};
+use mutation::MutationSettings;
+use policy::{Policy, PolicySettings};
+use read_cache::ReadCache;
+use schema::{CrdSchemas, SchemaValidation};
+use secrets::{Decrypter, DecryptionSettings};
+use settings::ProviderSettings;
+use validation::FieldValidation;
 
-const CERT_PATH_ERROR: &str =
-    "Certificate and key paths are not allowed for base64 encoded configs. Offending entry:";
 const FIELD_MANAGER: &str = "kubernetes-applier-provider";
 
 // main (via provider_main) initializes the threaded tokio executor,
@@ -33,20 +77,179 @@ const FIELD_MANAGER: &str = "kubernetes-applier-provider";
 //
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting provider process");
-    provider_main(
-        ApplierProvider::default(),
+    // We load the host data ourselves (rather than using provider_main) so we can read the
+    // provider settings from it
+    let host_data = load_host_data()?;
+    let settings = ProviderSettings::from_host_data(&host_data)?;
+    // Bound up front so a port that is already in use stops the provider from starting
+    let metrics_listener = settings
+        .metrics_port
+        .map(|port| {
+            let listener = std::net::TcpListener::bind((settings.metrics_address, port))?;
+            listener.set_nonblocking(true)?;
+            Ok::<_, std::io::Error>(listener)
+        })
+        .transpose()?;
+    if let Some(endpoint) = settings.otlp_endpoint.as_deref() {
+        telemetry::export_to(endpoint);
+    }
+    let provider = ApplierProvider::new(settings)?;
+
+    // This is what provider_start does, but with the metrics server running alongside the provider
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    if let Some(listener) = metrics_listener {
+        let provider = provider.clone();
+        runtime.spawn(metrics::serve(listener, move || {
+            let provider = provider.clone();
+            async move { provider.render_metrics().await }
+        }));
+    }
+    runtime.block_on(provider_run(
+        provider,
+        host_data,
         Some("Kubernetes Applier Provider".to_string()),
-    )?;
+    ))?;
+    runtime.shutdown_timeout(Duration::from_secs(10));
 
     info!("Applier provider exiting");
     Ok(())
 }
 
 /// applier capability provider implementation
-#[derive(Default, Clone, Provider)]
+#[derive(Clone, Provider)]
 #[services(KubernetesApplier)]
 struct ApplierProvider {
-    clients: Arc<RwLock<HashMap<String, Client>>>,
+    /// The clients of each link. Shared so requests don't copy a link's settings (e.g. its compiled
+    /// policy) every time they look it up
+    clients: Arc<RwLock<HashMap<String, Arc<LinkClients>>>>,
+    client_cache: ClientCache,
+    settings: Arc<ProviderSettings>,
+    /// Set once the settings worth warning about have been logged
+    settings_logged: Arc<Once>,
+    audit: Option<AuditLog>,
+    /// The metrics requests are recorded in, if they are served
+    metrics: Option<Metrics>,
+}
+
+impl ApplierProvider {
+    fn new(settings: ProviderSettings) -> Result<ApplierProvider, RpcError> {
+        let audit = settings
+            .audit_log
+            .as_ref()
+            .map(AuditLog::open)
+            .transpose()?;
+        Ok(ApplierProvider {
+            clients: Default::default(),
+            client_cache: ClientCache::new(settings.allow_insecure_tls),
+            settings_logged: Arc::new(Once::new()),
+            audit,
+            metrics: settings.metrics_port.map(|_| Metrics::default()),
+            settings: Arc::new(settings),
+        })
+    }
+
+    /// Logs the provider settings worth knowing about. Logging is only set up once the provider
+    /// runs, so this is done on the first link or health check rather than when it is created
+    fn log_settings(&self) {
+        self.settings_logged.call_once(|| {
+            if self.settings.allow_insecure_tls {
+                warn!("Provider is configured to allow insecure TLS, links may disable certificate verification");
+            }
+            if let Some(audit) = self.settings.audit_log.as_ref() {
+                info!(path = %audit.path, "Writing audit log");
+            }
+            if let Some(port) = self.settings.metrics_port {
+                info!(address = %self.settings.metrics_address, port, "Serving metrics");
+            }
+        });
+    }
+}
+
+/// The clients for all clusters registered on a single actor link
+struct LinkClients {
+    link_name: String,
+    default_cluster: String,
+    clusters: HashMap<String, ClusterClient>,
+    /// What this link last applied to each object, used to skip identical applies
+    applied: AppliedCache,
+    read_cache: Option<ReadCache>,
+    /// The results of the background probes of every cluster
+    health: LinkHealth,
+    limiter: Option<Arc<RateLimiter>>,
+    request_timeout: Option<Duration>,
+    warnings_as_errors: bool,
+    field_validation: Option<FieldValidation>,
+    schema_validation: Option<SchemaValidation>,
+    crd_schemas: CrdSchemas,
+    admission: Option<AdmissionSettings>,
+    policy: Option<Policy>,
+    mutations: Option<MutationSettings>,
+    images: Option<ImageRewriter>,
+    /// The signers applied manifests must be signed by, if the link verifies signatures
+    trusted_signers: Option<Vec<String>>,
+    decrypter: Option<Decrypter>,
+}
+
+impl LinkClients {
+    /// Returns the clusters a request should be sent to, either the named cluster (or the default if
+    /// no name is given) or all clusters when `all_clusters` is set
+    fn targets(
+        &self,
+        cluster: Option<&String>,
+        all_clusters: bool,
+    ) -> RpcResult<Vec<(String, ClusterClient)>> {
+        if all_clusters {
+            let mut targets: Vec<(String, ClusterClient)> = self
+                .clusters
+                .iter()
+                .map(|(name, client)| (name.clone(), client.clone()))
+                .collect();
+            targets.sort_by(|a, b| a.0.cmp(&b.0));
+            return Ok(targets);
+        }
+        let name = cluster
+            .map(|c| c.to_lowercase())
+            .unwrap_or_else(|| self.default_cluster.clone());
+        let client = self.clusters.get(&name).ok_or_else(|| {
+            RpcError::InvalidParameter(format!("No cluster named {} registered on link", name))
+        })?;
+        Ok(vec![(name, client.clone())])
+    }
+
+    /// Returns the deadline for a request with the given timeout, using the shorter of it and the
+    /// link's request timeout
+    fn deadline(&self, timeout_ms: Option<u32>) -> Option<Deadline> {
+        let requested = timeout_ms.map(|ms| Duration::from_millis(ms as u64));
+        match (self.request_timeout, requested) {
+            (Some(link), Some(requested)) => Some(link.min(requested)),
+            (link, requested) => link.or(requested),
+        }
+        .map(Deadline::after)
+    }
+
+    /// Waits until the link's limits allow another request to the API server or the deadline
+    /// passes. The returned permit must be held until the request is done
+    async fn acquire(&self, deadline: Option<Deadline>) -> Result<Option<Permit>, RequestError> {
+        match self.limiter.as_ref() {
+            Some(limiter) => Deadline::run(deadline, limiter.acquire())
+                .await
+                .and_then(|res| res)
+                .map(Some)
+                .map_err(|e| {
+                    warn!(error = %e, kind = %e.kind, "Rejecting request");
+                    e
+                }),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the single cluster a read should be sent to
+    fn target(&self, cluster: Option<&String>) -> RpcResult<(String, ClusterClient)> {
+        // SAFETY: A single target is always returned when not sending to all clusters
+        Ok(self.targets(cluster, false)?.remove(0))
+    }
 }
 
 impl ProviderDispatch for ApplierProvider {}
@@ -54,6 +257,7 @@ impl ProviderDispatch for ApplierProvider {}
 impl ProviderHandler for ApplierProvider {
     #[instrument(level = "debug", skip(self, ld), fields(actor_id = %ld.actor_id))]
     async fn put_link(&self, ld: &LinkDefinition) -> Result<bool, RpcError> {
+        self.log_settings();
         debug!("Got link request");
         // Normalize keys to lowercase
         let values: HashMap<String, String> = ld
@@ -62,252 +266,1299 @@ impl ProviderHandler for ApplierProvider {
             .map(|(k, v)| (k.to_lowercase(), v.to_owned()))
             .collect();
 
-        // Attempt to load the config. If nothing it passed attempt to infer it from the pod or the
-        // default kubeconfig path
-        let config = if let Some(p) = values.get(CONFIG_FILE_KEY) {
-            let path = p.to_owned();
-            debug!(%path, "Loading kubeconfig from file");
-            let conf = tokio::task::spawn_blocking(move || Kubeconfig::read_from(path))
-                .await
-                .map_err(|e| {
-                    RpcError::ProviderInit(format!(
-                        "Internal error occured while loading kubeconfig: {}",
-                        e
-                    ))
-                })?
-                .map_err(|e| format!("Invalid kubeconfig from file {}: {}", p, e))?;
-            Config::from_custom_kubeconfig(conf, &KubeConfigOptions::default())
-                .await
-                .map_err(|e| {
-                    RpcError::ProviderInit(format!("Invalid kubeconfig from file {}: {}", p, e))
-                })?
-        } else if let Some(raw) = values.get(CONFIG_B64_KEY) {
-            debug!("Loading config from base64 encoded string");
-            let decoded = base64::decode(raw).map_err(|e| {
-                RpcError::ProviderInit(format!("Invalid base64 config given: {}", e))
-            })?;
-            // NOTE: We do not support multiple yaml documents in the same file. We shouldn't need
-            // this, but if we do, we can borrow some of the logic from the `kube` crate
-            let conf: Kubeconfig = serde_yaml::from_slice(&decoded).map_err(|e| {
-                RpcError::ProviderInit(format!("Invalid kubeconfig data given: {}", e))
-            })?;
-            // Security: check that cert paths are not set as they could access certs on the host
-            // runtime
-            trace!("Ensuring base64 encoded config does not contain paths");
-            for cluster in conf.clusters.iter() {
-                ensure_no_path(
-                    &cluster.cluster.certificate_authority,
-                    "cluster",
-                    &cluster.name,
-                )?;
+        let sources = ClusterSources::from_values(&values)?;
+        let options = ClientOptions::from_values(&values, &ld.actor_id)?;
+        let read_cache_settings = ReadCacheSettings::from_values(&values)?;
+        // The cache watches every namespace, which the per actor identities impersonation is meant
+        // for usually aren't allowed to do
+        if read_cache_settings.is_some() && options.impersonation.is_some() {
+            return Err(RpcError::ProviderInit(
+                "The read cache can't be used with impersonation, as it watches all namespaces"
+                    .to_owned(),
+            ));
+        }
+
+        let mut clusters = HashMap::with_capacity(sources.clusters.len());
+        for settings in sources.clusters.iter() {
+            debug!(cluster = %settings.name, "Loading client for cluster");
+            let client = self.client_cache.get_or_connect(settings, &options).await?;
+            if client.is_insecure() {
+                warn!(cluster = %settings.name, "!!! INSECURE LINK: TLS certificate verification is disabled for this cluster. Credentials sent to the API server can be intercepted !!!");
             }
-            for user in conf.auth_infos.iter() {
-                ensure_no_path(
-                    &user.auth_info.client_certificate,
-                    "client_certificate",
-                    &user.name,
-                )?;
-                ensure_no_path(&user.auth_info.client_key, "client_key", &user.name)?;
-                ensure_no_path(&user.auth_info.token_file, "token_file", &user.name)?;
+            clusters.insert(settings.name.clone(), client);
+        }
+
+        let request_timeout = config::request_timeout(&values)?;
+        let warnings_as_errors = config::warnings_as_errors(&values)?;
+        let field_validation = validation::field_validation(&values)?;
+        let schema_validation = schema::schema_validation(&values)?;
+        let admission = AdmissionSettings::from_values(&values)?;
+        let policy = match PolicySettings::from_values(&values)? {
+            Some(settings) => Some(Policy::load(settings).await?),
+            None => None,
+        };
+        let mutations = MutationSettings::from_values(&values)?;
+        let decrypter = match DecryptionSettings::from_values(&values)? {
+            Some(settings) => Some(Decrypter::load(settings).await?),
+            None => None,
+        };
+        let trusted_signers = signature::trusted_signers(&values)?;
+        let trusted_signers = if self.settings.require_signed_manifests
+            || signature::verify_signatures(&values)?
+        {
+            if trusted_signers.is_empty() {
+                return Err(RpcError::ProviderInit(
+                    "Manifest signatures are verified but no trusted_signers are set".to_owned(),
+                ));
             }
-            Config::from_custom_kubeconfig(conf, &KubeConfigOptions::default())
-                .await
-                .map_err(|e| {
-                    RpcError::ProviderInit(format!("Invalid kubeconfig from base64: {}", e))
-                })?
+            Some(trusted_signers)
         } else {
-            debug!("No config given, inferring config from environment");
-            // If no config was manually specified we try to infer it from local pod variables or
-            // the default kubeconfig path
-            Config::infer().await.map_err(|e| RpcError::ProviderInit(format!("No config given and unable to infer config from environment or default config file: {}", e)))?
+            None
+        };
+        let images = match ImageSettings::from_values(&values)? {
+            Some(settings) => Some(ImageRewriter::load(settings).await?),
+            None => None,
         };
+        let limiter = LimitSettings::from_values(&values)?
+            .map(|settings| Arc::new(RateLimiter::new(&settings)));
+        let read_cache = read_cache_settings.map(|settings| {
+            debug!(kinds = ?settings.kinds, "Starting read cache");
+            ReadCache::start(&settings, &clusters)
+        });
 
-        tracing::trace!(?config, "Attempting to create client and connect to server");
-        // Now create the client and make sure it works
-        let client = Client::try_from(config).map_err(|e| {
-            RpcError::ProviderInit(format!(
-                "Unable to create client from loaded kubeconfig: {}",
-                e
-            ))
-        })?;
-
-        // NOTE: In the future, we may want to improve this with a retry
-        client.apiserver_version().await.map_err(|e| {
-            RpcError::ProviderInit(format!(
-                "Unable to connect to the Kubernetes API server: {}",
-                e
-            ))
-        })?;
-        tracing::trace!("Successfully connected to server");
+        let health = LinkHealth::start(&clusters);
 
         let mut clients = self.clients.write().await;
-        clients.insert(ld.actor_id.clone(), client);
+        clients.insert(
+            ld.actor_id.clone(),
+            Arc::new(LinkClients {
+                link_name: ld.link_name.clone(),
+                default_cluster: sources.default_cluster,
+                clusters,
+                applied: AppliedCache::default(),
+                read_cache,
+                health,
+                limiter,
+                request_timeout,
+                warnings_as_errors,
+                field_validation,
+                schema_validation,
+                crd_schemas: CrdSchemas::default(),
+                admission,
+                policy,
+                mutations,
+                images,
+                trusted_signers,
+                decrypter,
+            }),
+        );
         Ok(true)
     }
 
     async fn delete_link(&self, actor_id: &str) {
         self.clients.write().await.remove(actor_id);
     }
+
+    async fn shutdown(&self) -> Result<(), Infallible> {
+        // Entries still queued must make it to disk before the process exits
+        if let Some(audit) = self.audit.clone() {
+            if tokio::task::spawn_blocking(move || audit.close())
+                .await
+                .is_err()
+            {
+                error!("Unable to close the audit log");
+            }
+        }
+        Ok(())
+    }
+
+    async fn health_request(&self, _arg: &HealthCheckRequest) -> RpcResult<HealthCheckResponse> {
+        self.log_settings();
+        let clients = self.clients.read().await;
+        let mut endpoints: Vec<String> = clients
+            .iter()
+            .flat_map(|(actor_id, link)| {
+                let read_cache = link.read_cache.as_ref().map(|cache| {
+                    let (hits, misses) = cache.stats();
+                    let total = hits + misses;
+                    format!(
+                        "{}: read cache hit rate {:.1}% ({}/{})",
+                        actor_id,
+                        if total == 0 {
+                            0.0
+                        } else {
+                            hits as f64 * 100.0 / total as f64
+                        },
+                        hits,
+                        total
+                    )
+                });
+                link.clusters
+                    .iter()
+                    .map(move |(name, client)| {
+                        let (healthy, total) = client.endpoint_health();
+                        format!(
+                            "{}/{}: {} ({}/{} endpoints healthy{})",
+                            actor_id,
+                            name,
+                            client.active_endpoint(),
+                            healthy,
+                            total,
+                            if client.is_insecure() {
+                                ", INSECURE: TLS verification disabled"
+                            } else {
+                                ""
+                            }
+                        )
+                    })
+                    .chain(read_cache)
+            })
+            .collect();
+        endpoints.sort();
+
+        let mut failing: Vec<String> = clients
+            .iter()
+            .filter_map(|(actor_id, link)| {
+                let failures: Vec<String> = link
+                    .health
+                    .failures()
+                    .into_iter()
+                    .map(|(cluster, error)| format!("{}: {}", cluster, error))
+                    .collect();
+                (!failures.is_empty()).then(|| format!("{} ({})", actor_id, failures.join(", ")))
+            })
+            .collect();
+        failing.sort();
+        let mut message = endpoints.join(", ");
+        if !failing.is_empty() {
+            message = format!("Failing links: {}. {}", failing.join("; "), message);
+        }
+        Ok(HealthCheckResponse {
+            healthy: failing.is_empty(),
+            message: (!message.is_empty()).then_some(message),
+        })
+    }
 }
 
 #[async_trait]
 impl KubernetesApplier for ApplierProvider {
     #[instrument(level = "debug", skip(self, ctx, arg), fields(actor_id = ?ctx.actor, object_name = tracing::field::Empty))]
-    async fn apply(&self, ctx: &Context, arg: &Vec<u8>) -> RpcResult<OperationResponse> {
-        trace!(body_len = arg.len(), "Decoding object for apply");
-        let object: DynamicObject = serde_yaml::from_slice(arg).map_err(|e| {
-            RpcError::InvalidParameter(format!("Unable to parse data as kubernetes object: {}", e))
-        })?;
+    async fn apply(&self, ctx: &Context, arg: &ApplyRequest) -> RpcResult<OperationResponse> {
+        let started = Instant::now();
+        trace!(body_len = arg.manifest.len(), "Decoding object for apply");
+        let actor_id = ctx.actor.as_deref().unwrap_or_default();
+        // Requests rejected before they are handled are still audited, with whatever we know of them
+        let (mut object, obj_name, gvk) = match parse_manifest(&arg.manifest) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let result = Err(e);
+                self.audit(
+                    &audit::Request {
+                        operation: "Apply",
+                        actor_id,
+                        link_name: None,
+                        group: "",
+                        version: "",
+                        kind: "",
+                        namespace: None,
+                        name: "",
+                        manifest: Some(&arg.manifest),
+                    },
+                    &result,
+                );
+                return result;
+            }
+        };
+        tracing::span::Span::current().record("object_name", &tracing::field::display(&obj_name));
+        trace!(?gvk, "Inferred object type from data");
+        let resource = ApiResource::from_gvk(&gvk);
 
-        let obj_name = object
-            .metadata
-            .name
-            .as_ref()
-            .ok_or_else(|| {
-                RpcError::InvalidParameter("The given object is missing a name".to_string())
-            })?
-            .as_str();
+        let link = match self.get_link(ctx).await {
+            Ok(link) => link,
+            Err(e) => {
+                let result = Err(e);
+                self.audit(
+                    &audit::Request {
+                        operation: "Apply",
+                        actor_id,
+                        link_name: None,
+                        group: &gvk.group,
+                        version: &gvk.version,
+                        kind: &gvk.kind,
+                        namespace: object.metadata.namespace.as_deref(),
+                        name: &obj_name,
+                        manifest: Some(&arg.manifest),
+                    },
+                    &result,
+                );
+                return result;
+            }
+        };
+        // Wrapped so every outcome, including requests the provider rejects, ends up in the audit log
+        // and metrics
+        let (result, calls) = metrics::track(async {
+            if let Some(signers) = link.trusted_signers.as_deref() {
+                if let Err(e) = signature::verify(signers, &arg.manifest, arg.signature.as_ref()) {
+                    warn!(error = %e.message, "Rejecting manifest");
+                    return Ok(e.into());
+                }
+            }
+            if secrets::is_encrypted(&object) && link.decrypter.is_none() {
+                return Err(RpcError::InvalidParameter(
+                    "The given Secret has encrypted values but the link has no age identity to decrypt them with"
+                        .to_string(),
+                ));
+            }
+            let options = ApplyOptions {
+                force: arg.force,
+                decrypter: link.decrypter.clone(),
+                warnings_as_errors: link.warnings_as_errors,
+                field_validation: match arg.field_validation.as_deref() {
+                    Some(raw) => Some(raw.parse::<FieldValidation>().map_err(|e| {
+                        RpcError::InvalidParameter(format!("Invalid field validation {}: {}", raw, e))
+                    })?),
+                    None => link.field_validation,
+                },
+            };
+            let targets = link.targets(arg.cluster.as_ref(), arg.all_clusters)?;
 
-        tracing::span::Span::current().record("object_name", &tracing::field::display(obj_name));
+            // SAFETY: A DynamicObject is always representable as JSON
+            let mut value = serde_json::to_value(&object).unwrap();
 
-        let type_data = object.types.as_ref().ok_or_else(|| {
-            RpcError::InvalidParameter(
-                "The given manifest does not contain type information".to_string(),
-            )
-        })?;
-        // Decompose api_version into the parts we need to type the request
-        let (group, version) = match type_data.api_version.split_once('/') {
-            Some((g, v)) => (g.to_owned(), v.to_owned()),
-            None => (String::new(), type_data.api_version.to_owned()),
-        };
-        let gvk = GroupVersionKind {
-            group,
-            version,
-            kind: type_data.kind.clone(),
-        };
-        let resource = ApiResource::from_gvk(&gvk);
+            // Defaults are filled in first so every check sees the object that is actually applied
+            let mut mutations = match link.mutations.as_ref() {
+                Some(settings) => mutation::mutate(settings, &mut value),
+                None => Vec::new(),
+            };
+            if let Some(images) = link.images.as_ref() {
+                mutations.extend(images.rewrite_object(&mut value));
+            }
+            if !mutations.is_empty() {
+                debug!(?mutations, "Filled in defaults for object");
+                object = serde_json::from_value(value.clone()).map_err(|e| {
+                    RpcError::InvalidParameter(format!("Unable to fill in defaults for object: {}", e))
+                })?;
+            }
+            let desired = DesiredObject::new(object.clone(), obj_name.clone(), resource);
+            // Checks are run without the encrypted values, which don't match the schema of the fields
+            secrets::redact(&mut value);
 
-        trace!(?gvk, "Inferred object type from data");
+            // Built-in kinds are checked once up front, custom resources against each cluster's CRDs
+            let mut custom_validation = None;
+            if let Some(mode) = link.schema_validation {
+                let strict = mode == SchemaValidation::Strict;
+                match schema::validate_builtin(&value, strict) {
+                    Some(errors) if !errors.is_empty() => {
+                        debug!(?errors, "Object failed schema validation");
+                        return Ok(OperationResponse {
+                            succeeded: false,
+                            error: Some(schema_error(&errors)),
+                            error_kind: Some(ErrorKind::Invalid.into()),
+                            validation_errors: errors,
+                            ..Default::default()
+                        });
+                    }
+                    Some(_) => {}
+                    None => custom_validation = Some(strict),
+                }
+            }
 
-        let client = self.get_client(ctx).await?;
+            if let Some(settings) = link.admission.as_ref() {
+                let violations = admission::check(settings, &value);
+                if !violations.is_empty() {
+                    warn!(?violations, "Denying object that violates policy");
+                    return Ok(OperationResponse {
+                        succeeded: false,
+                        error: Some(format!(
+                            "Object violates policy: {}",
+                            admission::describe(&violations)
+                        )),
+                        error_kind: Some(ErrorKind::PolicyDenied.into()),
+                        policy_violations: violations,
+                        ..Default::default()
+                    });
+                }
+            }
 
-        let api: Api<DynamicObject> = if let Some(ns) = object.metadata.namespace.as_ref() {
-            Api::namespaced_with(client, ns.as_str(), &resource)
-        } else {
-            Api::default_namespaced_with(client, &resource)
-        };
+            if let Some(policy) = link.policy.as_ref() {
+                let violations = policy.evaluate(&policy::Input {
+                    operation: "Apply",
+                    actor_id,
+                    link_name: &link.link_name,
+                    clusters: targets.iter().map(|(name, _)| name.as_str()).collect(),
+                    object: Some(&value),
+                    resource: policy::Resource {
+                        group: &gvk.group,
+                        version: &gvk.version,
+                        kind: &gvk.kind,
+                        namespace: object.metadata.namespace.as_deref(),
+                        name: &obj_name,
+                    },
+                });
+                if !violations.is_empty() {
+                    return Ok(policy_denied(violations));
+                }
+            }
+
+            let deadline = link.deadline(arg.timeout_ms);
+
+            let (link, desired, options) = (&link, &desired, &options);
+            let value = &value;
+            let results = join_all(targets.into_iter().map(|(cluster, client)| async move {
+                let key = desired.key(&cluster, &client);
+                // Every cluster sent to counts against the link's limits
+                let _permit = match link.acquire(deadline).await {
+                    Ok(p) => p,
+                    Err(e) => return failed_result(cluster, e),
+                };
+                let applying = async {
+                    if let Some(strict) = custom_validation {
+                        if let Some(schema) = link
+                            .crd_schemas
+                            .get(&cluster, &client, &desired.resource)
+                            .await
+                        {
+                            let errors = schema::validate_custom(value, &schema, strict);
+                            if !errors.is_empty() {
+                                debug!(?errors, %cluster, "Object failed schema validation");
+                                return invalid_result(cluster.clone(), errors);
+                            }
+                        }
+                    }
+                    apply_object(
+                        cluster.clone(),
+                        client,
+                        &link.applied,
+                        link.read_cache.as_ref(),
+                        desired,
+                        options,
+                    )
+                    .await
+                };
+                let (result, warnings) = warnings::collect(Deadline::run(deadline, applying)).await;
+                let result = with_warnings(
+                    result.unwrap_or_else(|e| failed_result(cluster.clone(), e)),
+                    warnings,
+                    link.warnings_as_errors,
+                );
+                if !result.succeeded {
+                    // A timed out apply may still go through and one with warnings went through, so
+                    // the next apply must not be skipped
+                    link.applied.remove(&key);
+                }
+                result
+            }))
+            .await;
 
-        debug!("Attempting to apply object to api");
+            Ok(OperationResponse {
+                mutations,
+                ..to_response(results)
+            })
+        })
+        .await;
+        self.audit(
+            &audit::Request {
+                operation: "Apply",
+                actor_id,
+                link_name: Some(&link.link_name),
+                group: &gvk.group,
+                version: &gvk.version,
+                kind: &gvk.kind,
+                namespace: object.metadata.namespace.as_deref(),
+                name: &obj_name,
+                manifest: Some(&arg.manifest),
+            },
+            &result,
+        );
+        self.observe(
+            &metrics::RequestLabels {
+                actor_id,
+                operation: "Apply",
+                group: &gvk.group,
+                version: &gvk.version,
+                kind: &gvk.kind,
+                outcome: operation_outcome(&result),
+            },
+            started,
+            calls,
+        );
+        result
+    }
 
-        trace!("Checking if object already exists");
-        let exists = match api.get(obj_name).await {
-            Ok(_) => true,
-            Err(kube::Error::Api(e)) if e.code == 404 => false,
-            // TODO: retries in case of flakiness?
+    #[instrument(level = "debug", skip(self, ctx), fields(actor_id = ?ctx.actor))]
+    async fn delete(&self, ctx: &Context, arg: &DeleteRequest) -> RpcResult<OperationResponse> {
+        let started = Instant::now();
+        let request = |link_name| audit::Request {
+            operation: "Delete",
+            actor_id: ctx.actor.as_deref().unwrap_or_default(),
+            link_name,
+            group: &arg.group,
+            version: &arg.version,
+            kind: &arg.kind,
+            namespace: arg.namespace.as_deref(),
+            name: &arg.name,
+            manifest: None,
+        };
+        let link = match self.get_link(ctx).await {
+            Ok(link) => link,
             Err(e) => {
-                return Ok(OperationResponse {
-                    succeeded: false,
-                    error: Some(format!("Unable to fetch object from API: {}", e)),
-                })
+                let result = Err(e);
+                self.audit(&request(None), &result);
+                return result;
             }
         };
+        // Wrapped so every outcome, including requests the provider rejects, ends up in the audit log
+        // and metrics
+        let (result, calls) = metrics::track(async {
+            let targets = link.targets(arg.cluster.as_ref(), arg.all_clusters)?;
+            if let Some(policy) = link.policy.as_ref() {
+                let violations = policy.evaluate(&policy::Input {
+                    operation: "Delete",
+                    actor_id: ctx.actor.as_deref().unwrap_or_default(),
+                    link_name: &link.link_name,
+                    clusters: targets.iter().map(|(name, _)| name.as_str()).collect(),
+                    object: None,
+                    resource: policy::Resource {
+                        group: &arg.group,
+                        version: &arg.version,
+                        kind: &arg.kind,
+                        namespace: arg.namespace.as_deref(),
+                        name: &arg.name,
+                    },
+                });
+                if !violations.is_empty() {
+                    return Ok(policy_denied(violations));
+                }
+            }
+            let deadline = link.deadline(arg.timeout_ms);
+
+            let resource = ApiResource::from_gvk(&GroupVersionKind {
+                group: arg.group.clone(),
+                version: arg.version.clone(),
+                kind: arg.kind.clone(),
+            });
+
+            let (link, resource) = (&link, &resource);
+            let results = join_all(targets.into_iter().map(|(cluster, client)| async move {
+                // Whatever happens, we no longer know the state of the object
+                link.applied.remove(&ObjectKey::new(
+                    &cluster,
+                    &arg.group,
+                    &arg.kind,
+                    arg.namespace.as_deref(),
+                    &client.default_namespace(),
+                    &arg.name,
+                ));
+                // Every cluster sent to counts against the link's limits
+                let _permit = match link.acquire(deadline).await {
+                    Ok(p) => p,
+                    Err(e) => return failed_result(cluster, e),
+                };
+                let deleting = delete_object(
+                    cluster.clone(),
+                    client,
+                    arg.namespace.as_deref(),
+                    arg.name.as_str(),
+                    resource,
+                );
+                let (result, warnings) = warnings::collect(Deadline::run(deadline, deleting)).await;
+                with_warnings(
+                    result.unwrap_or_else(|e| failed_result(cluster, e)),
+                    warnings,
+                    link.warnings_as_errors,
+                )
+            }))
+            .await;
+
+            Ok(to_response(results))
+        })
+        .await;
+        self.audit(&request(Some(&link.link_name)), &result);
+        self.observe(
+            &metrics::RequestLabels {
+                actor_id: ctx.actor.as_deref().unwrap_or_default(),
+                operation: "Delete",
+                group: &arg.group,
+                version: &arg.version,
+                kind: &arg.kind,
+                outcome: operation_outcome(&result),
+            },
+            started,
+            calls,
+        );
+        result
+    }
+
+    #[instrument(level = "debug", skip(self, ctx), fields(actor_id = ?ctx.actor))]
+    async fn get(&self, ctx: &Context, arg: &GetRequest) -> RpcResult<GetResponse> {
+        let started = Instant::now();
+        let (result, calls) = metrics::track(async {
+            let link = self.get_link(ctx).await?;
+            let (cluster, client) = link.target(arg.cluster.as_ref())?;
+            let resource = ApiResource::from_gvk(&GroupVersionKind {
+                group: arg.group.clone(),
+                version: arg.version.clone(),
+                kind: arg.kind.clone(),
+            });
+            let namespace = arg.namespace.as_deref();
+            let name = arg.name.as_str();
 
-        let resp = if exists {
-            trace!("Object already exists, attempting server-side apply");
-            api.patch(
-                obj_name,
-                &PatchParams {
-                    field_manager: Some(FIELD_MANAGER.to_string()),
+            if let Some(cached) = link
+                .read_cache
+                .as_ref()
+                .and_then(|c| c.get(&cluster, &resource, namespace, name))
+            {
+                trace!("Serving get from read cache");
+                return Ok(GetResponse {
+                    succeeded: true,
+                    found: cached.is_some(),
+                    object: cached.map(|obj| encode_object(&obj)).transpose()?,
+                    from_cache: true,
                     ..Default::default()
-                },
-                &Patch::Apply(&object),
-            )
-            .await
-        } else {
-            trace!("Object does not exist, creating");
-            api.create(
-                &PostParams {
-                    field_manager: Some(FIELD_MANAGER.to_string()),
+                });
+            }
+
+            let deadline = link.deadline(arg.timeout_ms);
+            let _permit = match link.acquire(deadline).await {
+                Ok(p) => p,
+                Err(e) => return Ok(e.into()),
+            };
+            let resource = &resource;
+            let span = telemetry::request_span("Get", &cluster, resource, namespace, Some(name));
+            let getting = telemetry::traced(
+                span,
+                client.call(
+                    |c| async move { dynamic_api(c, namespace, resource).get_opt(name).await },
+                ),
+            );
+            let (resp, warnings) = warnings::collect(Deadline::run(deadline, getting)).await;
+            let resp = match resp {
+                Ok(resp) => resp,
+                Err(e) => return Ok(e.into()),
+            };
+            if link.warnings_as_errors && !warnings.is_empty() {
+                return Ok(GetResponse {
+                    warnings: warnings.clone(),
+                    ..warnings_error(&warnings).into()
+                });
+            }
+            match resp {
+                Ok(obj) => Ok(GetResponse {
+                    succeeded: true,
+                    found: obj.is_some(),
+                    object: obj.map(|obj| encode_object(&obj)).transpose()?,
+                    warnings,
                     ..Default::default()
+                }),
+                Err(e) => Ok(GetResponse {
+                    succeeded: false,
+                    error: Some(e.to_string()),
+                    warnings,
+                    ..Default::default()
+                }),
+            }
+        })
+        .await;
+        self.observe(
+            &metrics::RequestLabels {
+                actor_id: ctx.actor.as_deref().unwrap_or_default(),
+                operation: "Get",
+                group: &arg.group,
+                version: &arg.version,
+                kind: &arg.kind,
+                outcome: match &result {
+                    Ok(resp) if resp.succeeded => "Succeeded",
+                    _ => "Failed",
                 },
-                &object,
-            )
-            .await
-        };
+            },
+            started,
+            calls,
+        );
+        result
+    }
 
-        if let Err(e) = resp {
-            return Ok(OperationResponse {
-                succeeded: false,
-                error: Some(e.to_string()),
+    #[instrument(level = "debug", skip(self, ctx), fields(actor_id = ?ctx.actor))]
+    async fn list(&self, ctx: &Context, arg: &ListRequest) -> RpcResult<ListResponse> {
+        let started = Instant::now();
+        let (result, calls) = metrics::track(async {
+            let link = self.get_link(ctx).await?;
+            let (cluster, client) = link.target(arg.cluster.as_ref())?;
+            let resource = ApiResource::from_gvk(&GroupVersionKind {
+                group: arg.group.clone(),
+                version: arg.version.clone(),
+                kind: arg.kind.clone(),
             });
-        }
+            let namespace = arg.namespace.as_deref();
+            let label_selector = arg.label_selector.as_deref();
 
-        Ok(OperationResponse {
-            succeeded: true,
-            error: None,
+            if let Some(cached) = link
+                .read_cache
+                .as_ref()
+                .and_then(|c| c.list(&cluster, &resource, namespace, label_selector))
+            {
+                trace!(count = cached.len(), "Serving list from read cache");
+                return Ok(ListResponse {
+                    succeeded: true,
+                    objects: cached
+                        .iter()
+                        .map(|obj| encode_object(obj))
+                        .collect::<RpcResult<_>>()?,
+                    from_cache: true,
+                    ..Default::default()
+                });
+            }
+
+            let deadline = link.deadline(arg.timeout_ms);
+            let _permit = match link.acquire(deadline).await {
+                Ok(p) => p,
+                Err(e) => return Ok(e.into()),
+            };
+            let resource = &resource;
+            let params = &ListParams {
+                label_selector: label_selector.map(str::to_owned),
+                ..Default::default()
+            };
+            let span = telemetry::request_span("List", &cluster, resource, namespace, None);
+            let listing = telemetry::traced(
+                span,
+                client.call(|c| async move {
+                    let api = match namespace {
+                        Some(ns) => Api::<DynamicObject>::namespaced_with(c, ns, resource),
+                        None => Api::all_with(c, resource),
+                    };
+                    api.list(params).await
+                }),
+            );
+            let (resp, warnings) = warnings::collect(Deadline::run(deadline, listing)).await;
+            let resp = match resp {
+                Ok(resp) => resp,
+                Err(e) => return Ok(e.into()),
+            };
+            if link.warnings_as_errors && !warnings.is_empty() {
+                return Ok(ListResponse {
+                    warnings: warnings.clone(),
+                    ..warnings_error(&warnings).into()
+                });
+            }
+            match resp {
+                Ok(list) => Ok(ListResponse {
+                    succeeded: true,
+                    objects: list
+                        .items
+                        .iter()
+                        .map(encode_object)
+                        .collect::<RpcResult<_>>()?,
+                    warnings,
+                    ..Default::default()
+                }),
+                Err(e) => Ok(ListResponse {
+                    succeeded: false,
+                    error: Some(e.to_string()),
+                    warnings,
+                    ..Default::default()
+                }),
+            }
         })
+        .await;
+        self.observe(
+            &metrics::RequestLabels {
+                actor_id: ctx.actor.as_deref().unwrap_or_default(),
+                operation: "List",
+                group: &arg.group,
+                version: &arg.version,
+                kind: &arg.kind,
+                outcome: match &result {
+                    Ok(resp) if resp.succeeded => "Succeeded",
+                    _ => "Failed",
+                },
+            },
+            started,
+            calls,
+        );
+        result
     }
+}
 
-    #[instrument(level = "debug", skip(self, ctx), fields(actor_id = ?ctx.actor))]
-    async fn delete(&self, ctx: &Context, arg: &DeleteRequest) -> RpcResult<OperationResponse> {
-        let client = self.get_client(ctx).await?;
+impl ApplierProvider {
+    /// Records an apply or delete in the audit log, if there is one
+    fn audit(&self, request: &audit::Request<'_>, result: &RpcResult<OperationResponse>) {
+        if let Some(audit) = self.audit.as_ref() {
+            audit.record(request, result);
+        }
+    }
 
-        let resource = ApiResource::from_gvk(&GroupVersionKind {
-            group: arg.group.clone(),
-            version: arg.version.clone(),
-            kind: arg.kind.clone(),
-        });
+    /// Records a finished request in the metrics, if they are served
+    fn observe(&self, request: &metrics::RequestLabels<'_>, started: Instant, calls: CallStats) {
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.observe(request, started.elapsed(), calls);
+        }
+    }
 
-        let api: Api<DynamicObject> = if let Some(ns) = arg.namespace.as_ref() {
-            Api::namespaced_with(client, ns.as_str(), &resource)
-        } else {
-            Api::default_namespaced_with(client, &resource)
-        };
-        debug!("Attempting to delete object");
-        match api
-            .delete(arg.name.as_str(), &DeleteParams::default())
+    /// Renders the metrics along with the current state of every link
+    async fn render_metrics(&self) -> String {
+        let mut links: Vec<metrics::LinkStats> = self
+            .clients
+            .read()
             .await
-        {
-            // If it is ok or returns not found, that means we are ok
-            Ok(_) => Ok(OperationResponse {
-                succeeded: true,
-                error: None,
-            }),
-            Err(kube::Error::Api(e)) if e.code == 404 => Ok(OperationResponse {
-                succeeded: true,
-                error: None,
-            }),
-            Err(e) => Ok(OperationResponse {
-                succeeded: false,
-                error: Some(e.to_string()),
-            }),
-        }
+            .iter()
+            .map(|(actor_id, link)| metrics::LinkStats {
+                actor_id: actor_id.clone(),
+                read_cache: link.read_cache.as_ref().map(ReadCache::stats),
+                watches: link
+                    .read_cache
+                    .as_ref()
+                    .map(ReadCache::watch_health)
+                    .unwrap_or_default(),
+            })
+            .collect();
+        links.sort_by(|a, b| a.actor_id.cmp(&b.actor_id));
+        self.metrics
+            .as_ref()
+            .map(|metrics| metrics.render(&links, self.client_cache.len()))
+            .unwrap_or_default()
     }
-}
 
-impl ApplierProvider {
-    async fn get_client(&self, ctx: &Context) -> RpcResult<Client> {
+    async fn get_link(&self, ctx: &Context) -> RpcResult<Arc<LinkClients>> {
         let actor_id = ctx.actor.as_ref().ok_or_else(|| {
             RpcError::InvalidParameter("Actor ID does not exist on request".to_string())
         })?;
-        Ok(self
-            .clients
+        self.clients
             .read()
             .await
             .get(actor_id.as_str())
+            .cloned()
             .ok_or_else(|| {
                 RpcError::InvalidParameter(format!("No link registered for actor {}", actor_id))
-            })?
-            .clone())
+            })
+    }
+}
+
+/// Per request options for applies
+struct ApplyOptions {
+    force: bool,
+    /// Decrypts encrypted Secret values right before the object is sent
+    decrypter: Option<Decrypter>,
+    warnings_as_errors: bool,
+    field_validation: Option<FieldValidation>,
+}
+
+/// An object to apply along with everything derived from it that is the same for every cluster
+struct DesiredObject {
+    /// The object with the desired state hash annotation added
+    object: DynamicObject,
+    name: String,
+    resource: ApiResource,
+    hash: String,
+}
+
+impl DesiredObject {
+    fn new(mut object: DynamicObject, name: String, resource: ApiResource) -> DesiredObject {
+        let hash = desired_state_hash(&object);
+        object
+            .metadata
+            .annotations
+            .get_or_insert_with(Default::default)
+            .insert(DESIRED_STATE_ANNOTATION.to_owned(), hash.clone());
+        DesiredObject {
+            object,
+            name,
+            resource,
+            hash,
+        }
+    }
+
+    fn key(&self, cluster: &str, client: &ClusterClient) -> ObjectKey {
+        ObjectKey::new(
+            cluster,
+            &self.resource.group,
+            &self.resource.kind,
+            self.object.metadata.namespace.as_deref(),
+            &client.default_namespace(),
+            &self.name,
+        )
+    }
+}
+
+fn encode_object(object: &DynamicObject) -> RpcResult<Vec<u8>> {
+    serde_json::to_vec(object)
+        .map_err(|e| RpcError::Ser(format!("Unable to encode object as JSON: {}", e)))
+}
+
+fn dynamic_api(
+    client: Client,
+    namespace: Option<&str>,
+    resource: &ApiResource,
+) -> Api<DynamicObject> {
+    match namespace {
+        Some(ns) => Api::namespaced_with(client, ns, resource),
+        None => Api::default_namespaced_with(client, resource),
+    }
+}
+
+#[instrument(level = "debug", skip(client, applied, read_cache, desired, options), fields(object_name = %desired.name))]
+async fn apply_object(
+    cluster: String,
+    client: ClusterClient,
+    applied: &AppliedCache,
+    read_cache: Option<&ReadCache>,
+    desired: &DesiredObject,
+    options: &ApplyOptions,
+) -> ClusterResult {
+    let key = desired.key(&cluster, &client);
+    let namespace = desired.object.metadata.namespace.as_deref();
+    if !options.force {
+        if let Some(state) = applied.get(&key).filter(|s| s.hash == desired.hash) {
+            // If we have a read cache for the object, we can also make sure nobody deleted it or
+            // applied something else since we last applied it
+            let current = read_cache
+                .and_then(|c| c.get(&cluster, &desired.resource, namespace, &desired.name));
+            let drifted = match current {
+                Some(None) => true,
+                Some(Some(obj)) => {
+                    obj.metadata
+                        .annotations
+                        .as_ref()
+                        .and_then(|a| a.get(DESIRED_STATE_ANNOTATION))
+                        != Some(&desired.hash)
+                }
+                None => false,
+            };
+            if !drifted {
+                debug!(resource_version = ?state.resource_version, "Object was already applied with identical manifest, skipping");
+                return ClusterResult {
+                    cluster,
+                    succeeded: true,
+                    unchanged: true,
+                    resource_version: state.resource_version,
+                    ..Default::default()
+                };
+            }
+            debug!("Object changed since it was last applied, applying again");
+        }
+    }
+
+    // The object is only decrypted once we know it will be sent, and never leaves this function
+    let decrypted;
+    let object = match options.decrypter.as_ref() {
+        Some(decrypter) if secrets::is_encrypted(&desired.object) => {
+            match decrypter.decrypt(&desired.object) {
+                Ok(object) => {
+                    decrypted = object;
+                    &decrypted
+                }
+                Err(e) => {
+                    return ClusterResult {
+                        cluster,
+                        succeeded: false,
+                        error: Some(e),
+                        ..Default::default()
+                    }
+                }
+            }
+        }
+        _ => &desired.object,
+    };
+    let (obj_name, resource) = (desired.name.as_str(), &desired.resource);
+    let patch = |dry_run: bool| {
+        let operation = if dry_run { "Apply (dry run)" } else { "Apply" };
+        let span =
+            telemetry::request_span(operation, &cluster, resource, namespace, Some(obj_name));
+        telemetry::traced(
+            span,
+            client.call(move |c| async move {
+                dynamic_api(c, namespace, resource)
+                    .patch(
+                        obj_name,
+                        &PatchParams {
+                            field_manager: Some(FIELD_MANAGER.to_string()),
+                            dry_run,
+                            field_validation: options
+                                .field_validation
+                                .map(FieldValidation::directive),
+                            ..Default::default()
+                        },
+                        &Patch::Apply(object),
+                    )
+                    .await
+            }),
+        )
+    };
+
+    if options.warnings_as_errors {
+        debug!("Checking object for warnings with a dry run");
+        let (resp, warnings) = warnings::collect(patch(true)).await;
+        if resp.is_ok() && !warnings.is_empty() {
+            return with_warnings(
+                ClusterResult {
+                    cluster,
+                    succeeded: true,
+                    ..Default::default()
+                },
+                warnings,
+                true,
+            );
+        }
+    }
+
+    debug!("Attempting to apply object to api");
+    // Server-side apply creates the object if it doesn't exist, so this is all we need
+    let resp = patch(false).await;
+
+    match resp {
+        Ok(obj) => {
+            let created = was_created(&obj);
+            trace!(created, "Successfully applied object");
+            applied.insert(
+                key,
+                AppliedState {
+                    hash: desired.hash.clone(),
+                    resource_version: obj.metadata.resource_version.clone(),
+                },
+            );
+            ClusterResult {
+                cluster,
+                succeeded: true,
+                created,
+                resource_version: obj.metadata.resource_version,
+                ..Default::default()
+            }
+        }
+        Err(e) => {
+            // We don't know what state the object is in now, so the next apply must go through
+            applied.remove(&key);
+            let validation_errors = validation::validation_errors(&e);
+            ClusterResult {
+                cluster,
+                succeeded: false,
+                error: Some(e.to_string()),
+                error_kind: (!validation_errors.is_empty()).then(|| ErrorKind::Invalid.into()),
+                validation_errors,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+/// Server-side apply returns the object whether it was created or updated, so we infer it from the
+/// returned metadata. An object we just created has a single managed fields entry, our apply,
+/// recorded at the creation time. Updates that change anything bump the entry's time (or add
+/// entries from other managers) and spec changes bump the generation. The only case this can't
+/// tell apart is an apply that changes nothing on an object no one else has touched since we
+/// created it, which is reported as created
+fn was_created(object: &DynamicObject) -> bool {
+    let meta = &object.metadata;
+    if meta.generation.unwrap_or(1) > 1 {
+        return false;
+    }
+    match (
+        meta.creation_timestamp.as_ref(),
+        meta.managed_fields.as_deref(),
+    ) {
+        (Some(created), Some([entry])) => {
+            entry.manager.as_deref() == Some(FIELD_MANAGER)
+                && entry.operation.as_deref() == Some("Apply")
+                && entry.time.as_ref() == Some(created)
+        }
+        _ => false,
+    }
+}
+
+#[instrument(level = "debug", skip(client, resource))]
+async fn delete_object(
+    cluster: String,
+    client: ClusterClient,
+    namespace: Option<&str>,
+    name: &str,
+    resource: &ApiResource,
+) -> ClusterResult {
+    debug!("Attempting to delete object");
+    let span = telemetry::request_span("Delete", &cluster, resource, namespace, Some(name));
+    let deleting = client.call(|c| async move {
+        dynamic_api(c, namespace, resource)
+            .delete(name, &DeleteParams::default())
+            .await
+    });
+    match telemetry::traced(span, deleting).await {
+        // If it is ok or returns not found, that means we are ok. Objects with finalizers are
+        // returned as they are while they wait to be deleted
+        Ok(deleted) => ClusterResult {
+            cluster,
+            succeeded: true,
+            resource_version: deleted.left().and_then(|obj| obj.metadata.resource_version),
+            ..Default::default()
+        },
+        Err(kube::Error::Api(e)) if e.code == 404 => ClusterResult {
+            cluster,
+            succeeded: true,
+            ..Default::default()
+        },
+        Err(e) => ClusterResult {
+            cluster,
+            succeeded: false,
+            error: Some(e.to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+/// Returns the error for a request the API server returned warnings to, when the link treats
+/// warnings as errors
+fn warnings_error(warnings: &[String]) -> RequestError {
+    RequestError::new(
+        ErrorKind::Warnings,
+        format!("API server returned warnings: {}", warnings.join("; ")),
+    )
+}
+
+/// Adds the warnings returned while handling a request to its result, failing it if the link
+/// treats warnings as errors
+fn with_warnings(
+    mut result: ClusterResult,
+    warnings: Vec<String>,
+    as_errors: bool,
+) -> ClusterResult {
+    for warning in warnings {
+        if !result.warnings.contains(&warning) {
+            result.warnings.push(warning);
+        }
+    }
+    if as_errors && result.succeeded && !result.warnings.is_empty() {
+        let e = warnings_error(&result.warnings);
+        result.succeeded = false;
+        result.error = Some(e.message);
+        result.error_kind = Some(e.kind.into());
+    }
+    result
+}
+
+/// Returns the response to a request denied by the link's policy
+fn policy_denied(violations: Vec<PolicyViolation>) -> OperationResponse {
+    warn!(?violations, "Policy denied request");
+    OperationResponse {
+        succeeded: false,
+        error: Some(format!(
+            "Request denied by policy: {}",
+            policy::describe(&violations)
+        )),
+        error_kind: Some(ErrorKind::PolicyDenied.into()),
+        policy_violations: violations,
+        ..Default::default()
+    }
+}
+
+/// Parses the manifest of an apply, returning the object with its name and type
+fn parse_manifest(manifest: &[u8]) -> RpcResult<(DynamicObject, String, GroupVersionKind)> {
+    let object: DynamicObject = serde_yaml::from_slice(manifest).map_err(|e| {
+        RpcError::InvalidParameter(format!("Unable to parse data as kubernetes object: {}", e))
+    })?;
+
+    let obj_name = object
+        .metadata
+        .name
+        .as_ref()
+        .ok_or_else(|| {
+            RpcError::InvalidParameter("The given object is missing a name".to_string())
+        })?
+        .to_owned();
+
+    let type_data = object.types.as_ref().ok_or_else(|| {
+        RpcError::InvalidParameter(
+            "The given manifest does not contain type information".to_string(),
+        )
+    })?;
+    // Decompose api_version into the parts we need to type the request
+    let (group, version) = match type_data.api_version.split_once('/') {
+        Some((g, v)) => (g.to_owned(), v.to_owned()),
+        None => (String::new(), type_data.api_version.to_owned()),
+    };
+    let gvk = GroupVersionKind {
+        group,
+        version,
+        kind: type_data.kind.clone(),
+    };
+    Ok((object, obj_name, gvk))
+}
+
+/// Returns the outcome of an apply or delete as it is reported in the metrics
+fn operation_outcome(result: &RpcResult<OperationResponse>) -> &'static str {
+    match result {
+        Ok(resp) => audit::outcome(resp),
+        Err(_) => "Failed",
+    }
+}
+
+fn schema_error(errors: &[ValidationError]) -> String {
+    format!(
+        "Object failed schema validation: {}",
+        schema::describe(errors)
+    )
+}
+
+/// Returns the result for a cluster the object failed schema validation on
+fn invalid_result(cluster: String, errors: Vec<ValidationError>) -> ClusterResult {
+    ClusterResult {
+        cluster,
+        succeeded: false,
+        error: Some(schema_error(&errors)),
+        error_kind: Some(ErrorKind::Invalid.into()),
+        validation_errors: errors,
+        ..Default::default()
     }
 }
 
-fn ensure_no_path(item: &Option<String>, entity: &str, name: &str) -> Result<(), RpcError> {
-    if item.is_some() {
-        return Err(RpcError::ProviderInit(format!(
-            "{} {} {}",
-            CERT_PATH_ERROR, entity, name
+/// Returns the result for a cluster the operation failed on with the given error
+fn failed_result(cluster: String, e: RequestError) -> ClusterResult {
+    ClusterResult {
+        cluster,
+        succeeded: false,
+        error: Some(e.message),
+        error_kind: Some(e.kind.into()),
+        ..Default::default()
+    }
+}
+
+/// Rolls up the results from each cluster into a single response. The operation only succeeds if it
+/// succeeded on every cluster. The error kind is only set if every failed cluster has the same kind
+fn to_response(results: Vec<ClusterResult>) -> OperationResponse {
+    let succeeded = results.iter().all(|r| r.succeeded);
+    let error = match results.as_slice() {
+        [single] => single.error.clone(),
+        _ if succeeded => None,
+        _ => Some(
+            results
+                .iter()
+                .filter_map(|r| r.error.as_ref().map(|e| format!("{}: {}", r.cluster, e)))
+                .collect::<Vec<_>>()
+                .join("; "),
+        ),
+    };
+    let mut kinds = results
+        .iter()
+        .filter(|r| !r.succeeded)
+        .map(|r| r.error_kind.as_ref());
+    let error_kind = match kinds.next() {
+        Some(Some(first)) if kinds.all(|k| k == Some(first)) => Some(first.clone()),
+        _ => None,
+    };
+    let mut warnings: Vec<String> = Vec::new();
+    for warning in results.iter().flat_map(|r| r.warnings.iter()) {
+        if !warnings.contains(warning) {
+            warnings.push(warning.clone());
+        }
+    }
+    let mut validation_errors: Vec<ValidationError> = Vec::new();
+    for e in results.iter().flat_map(|r| r.validation_errors.iter()) {
+        if !validation_errors.contains(e) {
+            validation_errors.push(e.clone());
+        }
+    }
+    OperationResponse {
+        succeeded,
+        error,
+        error_kind,
+        cluster_results: Some(results),
+        warnings,
+        validation_errors,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ManagedFieldsEntry, Time};
+    use kube::api::ObjectMeta;
+
+    use super::*;
+
+    fn object(generation: i64, entries: &[(&str, i64)]) -> DynamicObject {
+        let time = |offset| {
+            Time(k8s_openapi::chrono::DateTime::from_utc(
+                k8s_openapi::chrono::NaiveDateTime::from_timestamp(1_600_000_000 + offset, 0),
+                k8s_openapi::chrono::Utc,
+            ))
+        };
+        DynamicObject {
+            types: None,
+            metadata: ObjectMeta {
+                generation: Some(generation),
+                creation_timestamp: Some(time(0)),
+                managed_fields: Some(
+                    entries
+                        .iter()
+                        .map(|(manager, offset)| ManagedFieldsEntry {
+                            manager: Some(manager.to_string()),
+                            operation: Some("Apply".to_owned()),
+                            time: Some(time(*offset)),
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+            data: Default::default(),
+        }
+    }
+
+    #[test]
+    fn detect_created() {
+        assert!(was_created(&object(1, &[(FIELD_MANAGER, 0)])));
+        // Updated by us later on
+        assert!(!was_created(&object(1, &[(FIELD_MANAGER, 10)])));
+        assert!(!was_created(&object(2, &[(FIELD_MANAGER, 0)])));
+        // Touched by someone else
+        assert!(!was_created(&object(
+            1,
+            &[(FIELD_MANAGER, 0), ("kube-controller-manager", 0)]
         )));
     }
-    Ok(())
+
+    #[test]
+    fn response_error_kind() {
+        let ok = |cluster: &str| ClusterResult {
+            cluster: cluster.to_owned(),
+            succeeded: true,
+            ..Default::default()
+        };
+        let timed_out = |cluster: &str| {
+            failed_result(
+                cluster.to_owned(),
+                RequestError::new(ErrorKind::Timeout, "timed out"),
+            )
+        };
+
+        let resp = to_response(vec![ok("east"), timed_out("west")]);
+        assert!(!resp.succeeded);
+        assert_eq!(resp.error_kind.as_deref(), Some("Timeout"));
+
+        let other = ClusterResult {
+            cluster: "north".to_owned(),
+            error: Some("forbidden".to_owned()),
+            ..Default::default()
+        };
+        let resp = to_response(vec![timed_out("west"), other]);
+        assert!(resp.error_kind.is_none());
+
+        assert!(to_response(vec![ok("east")]).error_kind.is_none());
+    }
+
+    #[test]
+    fn warnings_as_errors() {
+        let ok = || ClusterResult {
+            cluster: "default".to_owned(),
+            succeeded: true,
+            ..Default::default()
+        };
+        let warnings = vec!["deprecated".to_owned(), "deprecated".to_owned()];
+
+        let result = with_warnings(ok(), warnings.clone(), false);
+        assert!(result.succeeded);
+        assert_eq!(result.warnings, vec!["deprecated"]);
+
+        let result = with_warnings(ok(), warnings, true);
+        assert!(!result.succeeded);
+        assert_eq!(result.error_kind.as_deref(), Some("Warnings"));
+        assert_eq!(to_response(vec![result]).warnings, vec!["deprecated"]);
+
+        assert!(with_warnings(ok(), Vec::new(), true).succeeded);
+    }
 }
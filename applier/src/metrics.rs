@@ -0,0 +1,449 @@
+//! Prometheus metrics for the requests the provider handles, served in the text exposition format.
+//! Request metrics are recorded as requests finish, everything else is read from the links when
+//! the metrics are scraped
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::Write,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use tracing::error;
+
+use crate::read_cache::WatchHealth;
+
+/// The upper bounds of the request duration histogram buckets, in seconds
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+tokio::task_local! {
+    /// The calls to the API server made by the request currently being handled
+    static CALLS: RefCell<CallStats>;
+}
+
+/// What happened to the API server calls made while handling a request
+#[derive(Debug, Default)]
+pub struct CallStats {
+    /// The cluster and status code of every error response
+    api_errors: Vec<(String, u16)>,
+    /// The cluster of every call that was retried on another endpoint
+    failovers: Vec<String>,
+}
+
+/// Runs the future, returning its output along with stats about the API server calls it made
+pub async fn track<F: Future>(fut: F) -> (F::Output, CallStats) {
+    CALLS
+        .scope(RefCell::new(CallStats::default()), async move {
+            let output = fut.await;
+            (output, CALLS.with(|c| c.take()))
+        })
+        .await
+}
+
+/// Records an error response from the API server for the request being handled
+pub fn record_api_error(cluster: &str, code: u16) {
+    let _ = CALLS.try_with(|c| c.borrow_mut().api_errors.push((cluster.to_owned(), code)));
+}
+
+/// Records a call that failed to connect and was retried on another endpoint
+pub fn record_failover(cluster: &str) {
+    let _ = CALLS.try_with(|c| c.borrow_mut().failovers.push(cluster.to_owned()));
+}
+
+/// The labels requests are counted by
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RequestLabels<'a> {
+    pub actor_id: &'a str,
+    pub operation: &'a str,
+    pub group: &'a str,
+    pub version: &'a str,
+    pub kind: &'a str,
+    pub outcome: &'a str,
+}
+
+impl RequestLabels<'_> {
+    fn to_labels(&self) -> Labels {
+        labels(&[
+            ("actor_id", self.actor_id),
+            ("operation", self.operation),
+            ("group", self.group),
+            ("version", self.version),
+            ("kind", self.kind),
+            ("outcome", self.outcome),
+        ])
+    }
+}
+
+/// The state of a single link at the time of a scrape
+pub struct LinkStats {
+    pub actor_id: String,
+    /// The read cache's hits and misses, if the link has one
+    pub read_cache: Option<(u64, u64)>,
+    pub watches: Vec<WatchHealth>,
+}
+
+/// Label names and values, in a fixed order
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Default)]
+struct Histogram {
+    /// The number of observations in each bucket, not including those in earlier buckets
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Default)]
+struct Registry {
+    durations: BTreeMap<Labels, Histogram>,
+    api_errors: BTreeMap<Labels, u64>,
+    failovers: BTreeMap<Labels, u64>,
+}
+
+/// The metrics recorded for finished requests. Clones record to the same metrics
+#[derive(Clone, Default)]
+pub struct Metrics {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Metrics {
+    /// Records a finished request along with the API server calls it made
+    pub fn observe(&self, request: &RequestLabels<'_>, duration: Duration, calls: CallStats) {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        let mut registry = self.registry.lock().unwrap();
+        let histogram = registry.durations.entry(request.to_labels()).or_default();
+        let secs = duration.as_secs_f64();
+        histogram.buckets.resize(DURATION_BUCKETS.len(), 0);
+        if let Some(idx) = DURATION_BUCKETS.iter().position(|bound| secs <= *bound) {
+            histogram.buckets[idx] += 1;
+        }
+        histogram.sum += secs;
+        histogram.count += 1;
+
+        for (cluster, code) in calls.api_errors {
+            let key = labels(&[
+                ("actor_id", request.actor_id),
+                ("cluster", &cluster),
+                ("code", &code.to_string()),
+            ]);
+            *registry.api_errors.entry(key).or_default() += 1;
+        }
+        for cluster in calls.failovers {
+            let key = labels(&[("actor_id", request.actor_id), ("cluster", &cluster)]);
+            *registry.failovers.entry(key).or_default() += 1;
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text format
+    pub fn render(&self, links: &[LinkStats], client_cache_size: usize) -> String {
+        let mut out = String::new();
+        {
+            // SAFETY: The lock is never held across an await or in code that can panic
+            let registry = self.registry.lock().unwrap();
+            header(
+                &mut out,
+                "applier_requests_total",
+                "counter",
+                "Requests handled by the provider",
+            );
+            for (labels, histogram) in registry.durations.iter() {
+                sample(&mut out, "applier_requests_total", labels, histogram.count);
+            }
+            header(
+                &mut out,
+                "applier_request_duration_seconds",
+                "histogram",
+                "How long requests took to handle",
+            );
+            for (labels, histogram) in registry.durations.iter() {
+                let mut cumulative = 0;
+                for (bound, count) in DURATION_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                    cumulative += count;
+                    let mut labels = labels.clone();
+                    labels.push(("le", bound.to_string()));
+                    sample(
+                        &mut out,
+                        "applier_request_duration_seconds_bucket",
+                        &labels,
+                        cumulative,
+                    );
+                }
+                let mut inf = labels.clone();
+                inf.push(("le", "+Inf".to_owned()));
+                sample(
+                    &mut out,
+                    "applier_request_duration_seconds_bucket",
+                    &inf,
+                    histogram.count,
+                );
+                sample(
+                    &mut out,
+                    "applier_request_duration_seconds_sum",
+                    labels,
+                    histogram.sum,
+                );
+                sample(
+                    &mut out,
+                    "applier_request_duration_seconds_count",
+                    labels,
+                    histogram.count,
+                );
+            }
+            header(
+                &mut out,
+                "applier_api_errors_total",
+                "counter",
+                "Error responses from the API server by status code",
+            );
+            for (labels, count) in registry.api_errors.iter() {
+                sample(&mut out, "applier_api_errors_total", labels, count);
+            }
+            header(
+                &mut out,
+                "applier_endpoint_failovers_total",
+                "counter",
+                "Calls retried on another API server endpoint after failing to connect",
+            );
+            for (labels, count) in registry.failovers.iter() {
+                sample(&mut out, "applier_endpoint_failovers_total", labels, count);
+            }
+        }
+
+        header(
+            &mut out,
+            "applier_active_links",
+            "gauge",
+            "Actor links currently registered",
+        );
+        sample(&mut out, "applier_active_links", &Vec::new(), links.len());
+        header(
+            &mut out,
+            "applier_client_cache_size",
+            "gauge",
+            "Cluster clients currently shared between links",
+        );
+        sample(
+            &mut out,
+            "applier_client_cache_size",
+            &Vec::new(),
+            client_cache_size,
+        );
+
+        header(
+            &mut out,
+            "applier_read_cache_hits_total",
+            "counter",
+            "Reads served from the read cache",
+        );
+        for link in links {
+            if let Some((hits, _)) = link.read_cache {
+                let labels = labels(&[("actor_id", &link.actor_id)]);
+                sample(&mut out, "applier_read_cache_hits_total", &labels, hits);
+            }
+        }
+        header(
+            &mut out,
+            "applier_read_cache_misses_total",
+            "counter",
+            "Reads of cached kinds that had to go to the API server",
+        );
+        for link in links {
+            if let Some((_, misses)) = link.read_cache {
+                let labels = labels(&[("actor_id", &link.actor_id)]);
+                sample(&mut out, "applier_read_cache_misses_total", &labels, misses);
+            }
+        }
+
+        header(
+            &mut out,
+            "applier_watch_healthy",
+            "gauge",
+            "Whether a read cache watch has synced and is running without errors",
+        );
+        for link in links {
+            for watch in link.watches.iter() {
+                let labels = watch_labels(&link.actor_id, watch);
+                sample(
+                    &mut out,
+                    "applier_watch_healthy",
+                    &labels,
+                    u8::from(watch.healthy),
+                );
+            }
+        }
+        header(
+            &mut out,
+            "applier_watch_restarts_total",
+            "counter",
+            "Times a read cache watch failed and was restarted",
+        );
+        for link in links {
+            for watch in link.watches.iter() {
+                let labels = watch_labels(&link.actor_id, watch);
+                sample(
+                    &mut out,
+                    "applier_watch_restarts_total",
+                    &labels,
+                    watch.restarts,
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Serves the metrics rendered by the given function on `/metrics` until the process exits
+pub async fn serve<F, Fut>(listener: std::net::TcpListener, render: F)
+where
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = String> + Send,
+{
+    let server = match Server::from_tcp(listener) {
+        Ok(server) => server,
+        Err(e) => {
+            error!(error = %e, "Unable to start metrics server");
+            return;
+        }
+    };
+    let make_service = make_service_fn(move |_| {
+        let render = render.clone();
+        async move {
+            Ok::<_, std::convert::Infallible>(service_fn(move |req: Request<Body>| {
+                let render = render.clone();
+                async move {
+                    let resp = if req.method() == Method::GET && req.uri().path() == "/metrics" {
+                        Response::builder()
+                            .header("content-type", "text/plain; version=0.0.4")
+                            .body(Body::from(render().await))
+                    } else {
+                        Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .body(Body::empty())
+                    };
+                    Ok::<_, std::convert::Infallible>(resp.expect("metrics responses are valid"))
+                }
+            }))
+        }
+    });
+    if let Err(e) = server.serve(make_service).await {
+        error!(error = %e, "Metrics server failed");
+    }
+}
+
+fn labels(pairs: &[(&'static str, &str)]) -> Labels {
+    pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+}
+
+fn watch_labels(actor_id: &str, watch: &WatchHealth) -> Labels {
+    labels(&[
+        ("actor_id", actor_id),
+        ("cluster", &watch.cluster),
+        ("api_version", &watch.api_version),
+        ("kind", &watch.kind),
+    ])
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &Labels, value: impl std::fmt::Display) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let rendered: Vec<String> = labels
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+            .collect();
+        let _ = write!(out, "{{{}}}", rendered.join(","));
+    }
+    let _ = writeln!(out, " {}", value);
+}
+
+/// Escapes a label value as required by the text format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn render_metrics() {
+        let metrics = Metrics::default();
+        let ((), calls) = track(async {
+            record_api_error("prod", 409);
+            record_failover("prod");
+        })
+        .await;
+        // Nothing is recorded outside of track
+        record_api_error("prod", 500);
+
+        let request = RequestLabels {
+            actor_id: "MACTOR",
+            operation: "Apply",
+            group: "apps",
+            version: "v1",
+            kind: "Deployment",
+            outcome: "Succeeded",
+        };
+        metrics.observe(&request, Duration::from_millis(30), calls);
+        metrics.observe(&request, Duration::from_secs(60), CallStats::default());
+
+        let links = vec![LinkStats {
+            actor_id: "MACTOR".to_owned(),
+            read_cache: Some((3, 1)),
+            watches: vec![WatchHealth {
+                cluster: "prod".to_owned(),
+                api_version: "v1".to_owned(),
+                kind: "Pod".to_owned(),
+                healthy: true,
+                restarts: 2,
+            }],
+        }];
+        let out = metrics.render(&links, 1);
+        let labels = r#"actor_id="MACTOR",operation="Apply",group="apps",version="v1",kind="Deployment",outcome="Succeeded""#;
+        for expected in [
+            format!("applier_requests_total{{{}}} 2", labels),
+            format!(
+                "applier_request_duration_seconds_bucket{{{},le=\"0.05\"}} 1",
+                labels
+            ),
+            format!(
+                "applier_request_duration_seconds_bucket{{{},le=\"30\"}} 1",
+                labels
+            ),
+            format!(
+                "applier_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 2",
+                labels
+            ),
+            r#"applier_api_errors_total{actor_id="MACTOR",cluster="prod",code="409"} 1"#.to_owned(),
+            r#"applier_endpoint_failovers_total{actor_id="MACTOR",cluster="prod"} 1"#.to_owned(),
+            "applier_active_links 1".to_owned(),
+            "applier_client_cache_size 1".to_owned(),
+            r#"applier_read_cache_hits_total{actor_id="MACTOR"} 3"#.to_owned(),
+            r#"applier_watch_healthy{actor_id="MACTOR",cluster="prod",api_version="v1",kind="Pod"} 1"#
+                .to_owned(),
+        ] {
+            assert!(out.lines().any(|l| l == expected), "missing {}", expected);
+        }
+        assert!(!out.contains("code=\"500\""));
+    }
+
+    #[test]
+    fn escape_labels() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,321 @@
+//! Fills in the defaults a link's mutation settings give for labels, annotations, image pull secrets
+//! and container resources. Values the object already sets are never overwritten
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+use wasmbus_rpc::error::RpcError;
+
+use crate::{admission, config::key_values};
+
+/// Labels added to every applied object (and its pod template) that doesn't already set them, as a
+/// comma delimited list of `key=value` pairs
+const DEFAULT_LABELS_KEY: &str = "default_labels";
+/// Annotations added to every applied object that doesn't already set them, in the same format as
+/// `default_labels`
+const DEFAULT_ANNOTATIONS_KEY: &str = "default_annotations";
+/// A comma delimited list of secrets added to the `imagePullSecrets` of every pod spec
+const IMAGE_PULL_SECRETS_KEY: &str = "image_pull_secrets";
+/// Resource requests set on containers that don't set them, e.g. `cpu=100m,memory=128Mi`
+const DEFAULT_REQUESTS_KEY: &str = "default_requests";
+/// Resource limits set on containers that don't set them, in the same format as `default_requests`
+const DEFAULT_LIMITS_KEY: &str = "default_limits";
+
+/// The fields of containers that get default resources. Ephemeral containers can't set resources
+const RESOURCE_CONTAINER_FIELDS: &[&str] = &["initContainers", "containers"];
+
+/// The defaults filled in on every object a link applies
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MutationSettings {
+    pub labels: Vec<(String, String)>,
+    pub annotations: Vec<(String, String)>,
+    pub image_pull_secrets: Vec<String>,
+    pub requests: Vec<(String, String)>,
+    pub limits: Vec<(String, String)>,
+}
+
+impl MutationSettings {
+    /// Parses the mutation settings from the given (lowercased) link values. Returns `None` if no
+    /// defaults are set
+    pub fn from_values(
+        values: &HashMap<String, String>,
+    ) -> Result<Option<MutationSettings>, RpcError> {
+        let settings = MutationSettings {
+            labels: key_values(values, DEFAULT_LABELS_KEY)?,
+            annotations: key_values(values, DEFAULT_ANNOTATIONS_KEY)?,
+            image_pull_secrets: values
+                .get(IMAGE_PULL_SECRETS_KEY)
+                .map(|raw| {
+                    raw.split(',')
+                        .map(|s| s.trim().to_owned())
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            requests: key_values(values, DEFAULT_REQUESTS_KEY)?,
+            limits: key_values(values, DEFAULT_LIMITS_KEY)?,
+        };
+        if settings == MutationSettings::default() {
+            return Ok(None);
+        }
+        Ok(Some(settings))
+    }
+}
+
+/// Fills in the defaults missing from the object, returning the paths of every field that was set
+pub fn mutate(settings: &MutationSettings, object: &mut Value) -> Vec<String> {
+    let mut mutations = Vec::new();
+    let pod_path = admission::pod_spec_path(object);
+
+    fill(
+        object,
+        &["metadata", "labels"],
+        &settings.labels,
+        &mut mutations,
+    );
+    fill(
+        object,
+        &["metadata", "annotations"],
+        &settings.annotations,
+        &mut mutations,
+    );
+
+    let path = match pod_path {
+        Some(path) => path,
+        None => return mutations,
+    };
+    // Pods are labelled above, workloads also label the pods they create
+    if let Some(template) = path.strip_suffix(".spec") {
+        let mut keys: Vec<&str> = template.split('.').collect();
+        keys.extend(["metadata", "labels"]);
+        if object.pointer(&pointer(template)).is_some() {
+            fill(object, &keys, &settings.labels, &mut mutations);
+        }
+    }
+    let spec = match object.pointer_mut(&pointer(path)) {
+        Some(spec) if spec.is_object() => spec,
+        _ => return mutations,
+    };
+
+    if !settings.image_pull_secrets.is_empty() {
+        let secrets = spec.as_object_mut().and_then(|spec| {
+            spec.entry("imagePullSecrets")
+                .or_insert_with(|| Value::Array(Vec::new()))
+                .as_array_mut()
+        });
+        if let Some(secrets) = secrets {
+            for name in settings.image_pull_secrets.iter() {
+                let present = secrets
+                    .iter()
+                    .any(|s| s.get("name").and_then(Value::as_str) == Some(name));
+                if !present {
+                    secrets.push(serde_json::json!({ "name": name }));
+                    mutations.push(format!("{}.imagePullSecrets[{}]", path, secrets.len() - 1));
+                }
+            }
+        }
+    }
+
+    if !settings.requests.is_empty() || !settings.limits.is_empty() {
+        for list in RESOURCE_CONTAINER_FIELDS {
+            let containers = match spec.get_mut(list).and_then(Value::as_array_mut) {
+                Some(containers) => containers,
+                None => continue,
+            };
+            for (i, container) in containers.iter_mut().enumerate() {
+                let field = format!("{}.{}[{}]", path, list, i);
+                fill_resources(settings, container, &field, &mut mutations);
+            }
+        }
+    }
+    mutations
+}
+
+/// Fills in the default requests and limits of a single container
+fn fill_resources(
+    settings: &MutationSettings,
+    container: &mut Value,
+    field: &str,
+    mutations: &mut Vec<String>,
+) {
+    let sets = |container: &Value, kind: &str, name: &str| {
+        container
+            .pointer(&format!("/resources/{}/{}", kind, name))
+            .is_some()
+    };
+    // Without a request the API server uses the limit, and a request may be above a default limit,
+    // so a default is only used if neither is set for the resource
+    let requests: Vec<(String, String)> = settings
+        .requests
+        .iter()
+        .filter(|(name, _)| !sets(container, "requests", name) && !sets(container, "limits", name))
+        .cloned()
+        .collect();
+    let limits: Vec<(String, String)> = settings
+        .limits
+        .iter()
+        .filter(|(name, _)| !sets(container, "limits", name) && !sets(container, "requests", name))
+        .cloned()
+        .collect();
+    let mut added = Vec::new();
+    fill(container, &["resources", "requests"], &requests, &mut added);
+    fill(container, &["resources", "limits"], &limits, &mut added);
+    mutations.extend(added.into_iter().map(|f| format!("{}.{}", field, f)));
+}
+
+/// Sets the given keys of the object at the path that aren't already set, creating the object if
+/// needed. Nothing is set if something other than an object is in the way
+fn fill(
+    value: &mut Value,
+    path: &[&str],
+    defaults: &[(String, String)],
+    mutations: &mut Vec<String>,
+) {
+    if defaults.is_empty() {
+        return;
+    }
+    let map = match child_map(value, path) {
+        Some(map) => map,
+        None => return,
+    };
+    let prefix = path.join(".");
+    for (key, default) in defaults {
+        if !map.contains_key(key) {
+            map.insert(key.clone(), Value::String(default.clone()));
+            mutations.push(format!("{}.{}", prefix, key));
+        }
+    }
+}
+
+/// Returns the object at the given path, creating any that are missing along the way
+fn child_map<'a>(value: &'a mut Value, path: &[&str]) -> Option<&'a mut Map<String, Value>> {
+    path.iter()
+        .try_fold(value, |value, key| {
+            let child = value.as_object_mut()?.entry(*key).or_insert(Value::Null);
+            if child.is_null() {
+                *child = Value::Object(Map::new());
+            }
+            Some(child)
+        })?
+        .as_object_mut()
+}
+
+/// Converts a dot separated path into a JSON pointer
+fn pointer(path: &str) -> String {
+    format!("/{}", path.replace('.', "/"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::test::values;
+
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn fill_defaults() {
+        let settings = MutationSettings {
+            labels: pairs(&[("team", "platform"), ("app", "default")]),
+            annotations: pairs(&[("owner", "platform@example.com")]),
+            image_pull_secrets: vec!["regcred".to_owned(), "other".to_owned()],
+            requests: pairs(&[("cpu", "100m"), ("memory", "128Mi")]),
+            limits: pairs(&[("memory", "256Mi")]),
+        };
+        let mut object: Value = serde_yaml::from_str(
+            r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  labels:
+    app: web
+spec:
+  template:
+    metadata:
+      labels:
+        app: web
+    spec:
+      imagePullSecrets:
+        - name: other
+      containers:
+        - name: web
+          image: nginx
+          resources:
+            limits:
+              cpu: "1"
+"#,
+        )
+        .unwrap();
+
+        let mutations = mutate(&settings, &mut object);
+        assert_eq!(
+            mutations,
+            vec![
+                "metadata.labels.team",
+                "metadata.annotations.owner",
+                "spec.template.metadata.labels.team",
+                "spec.template.spec.imagePullSecrets[1]",
+                "spec.template.spec.containers[0].resources.requests.memory",
+                "spec.template.spec.containers[0].resources.limits.memory",
+            ]
+        );
+        assert_eq!(object["metadata"]["labels"]["app"], "web");
+        assert_eq!(
+            object["spec"]["template"]["metadata"]["labels"]["team"],
+            "platform"
+        );
+        let container = &object["spec"]["template"]["spec"]["containers"][0];
+        // The container's CPU limit is kept and no request is added below it
+        assert_eq!(
+            container["resources"],
+            serde_json::json!({
+                "limits": {"cpu": "1", "memory": "256Mi"},
+                "requests": {"memory": "128Mi"},
+            })
+        );
+
+        // Applying the defaults again changes nothing
+        assert!(mutate(&settings, &mut object).is_empty());
+
+        let mut config_map = serde_json::json!({"kind": "ConfigMap", "metadata": {"name": "foo"}});
+        assert_eq!(
+            mutate(&settings, &mut config_map),
+            vec![
+                "metadata.labels.team",
+                "metadata.labels.app",
+                "metadata.annotations.owner"
+            ]
+        );
+    }
+
+    #[test]
+    fn settings() {
+        assert!(MutationSettings::from_values(&HashMap::new())
+            .unwrap()
+            .is_none());
+        let settings = MutationSettings::from_values(&values(&[
+            ("default_labels", "team=platform, cost-center=1234"),
+            ("image_pull_secrets", "regcred,"),
+            ("default_requests", "cpu=100m,memory=128Mi"),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            settings.labels,
+            vec![
+                ("team".to_owned(), "platform".to_owned()),
+                ("cost-center".to_owned(), "1234".to_owned())
+            ]
+        );
+        assert!(settings.annotations.is_empty());
+        assert_eq!(settings.image_pull_secrets, vec!["regcred"]);
+        assert_eq!(settings.requests.len(), 2);
+        assert!(MutationSettings::from_values(&values(&[("default_labels", "team")])).is_err());
+    }
+}
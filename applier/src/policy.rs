@@ -0,0 +1,363 @@
+//! Evaluation of a link's Rego policy against the objects it applies and deletes. Policies are
+//! evaluated in-process, so they work without access to an OPA server
+
+use std::{collections::HashMap, path::Path};
+
+use kubernetes_applier_interface::PolicyViolation;
+use regorus::{CompiledPolicy, Engine};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::warn;
+use wasmbus_rpc::error::RpcError;
+
+use crate::config;
+
+/// The path to a Rego policy (a `.rego` file, or a directory of `.rego` files and `data.json`
+/// documents) applies and deletes are checked against
+const POLICY_FILE_KEY: &str = "policy_file";
+/// A base64 encoded Rego policy applies and deletes are checked against
+const POLICY_B64_KEY: &str = "policy_b64";
+/// The rule of the policy that returns the deny messages
+const POLICY_QUERY_KEY: &str = "policy_query";
+/// The default for `policy_query`
+const DEFAULT_POLICY_QUERY: &str = "data.applier.deny";
+
+/// The extension of files loaded as policy modules
+const REGO_EXTENSION: &str = "rego";
+/// The name of files in a policy directory loaded as data documents
+const DATA_FILE: &str = "data.json";
+
+/// Where a link's Rego policy is loaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicySource {
+    File(String),
+    Base64(String),
+}
+
+/// Settings for the Rego policy a link's requests are checked against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicySettings {
+    pub source: PolicySource,
+    pub query: String,
+}
+
+impl PolicySettings {
+    /// Parses the policy settings from the given (lowercased) link values. Returns `None` if no
+    /// policy is set
+    pub fn from_values(
+        values: &HashMap<String, String>,
+    ) -> Result<Option<PolicySettings>, RpcError> {
+        let source = match (values.get(POLICY_FILE_KEY), values.get(POLICY_B64_KEY)) {
+            (Some(_), Some(_)) => {
+                return Err(RpcError::ProviderInit(format!(
+                    "Only one of {} and {} can be set",
+                    POLICY_FILE_KEY, POLICY_B64_KEY
+                )))
+            }
+            (Some(path), None) => PolicySource::File(path.trim().to_owned()),
+            (None, Some(raw)) => PolicySource::Base64(raw.trim().to_owned()),
+            (None, None) => return Ok(None),
+        };
+        let query = values
+            .get(POLICY_QUERY_KEY)
+            .map(|q| q.trim().to_owned())
+            .unwrap_or_else(|| DEFAULT_POLICY_QUERY.to_owned());
+        Ok(Some(PolicySettings { source, query }))
+    }
+}
+
+/// The input a policy is evaluated against
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Input<'a> {
+    /// Either `Apply` or `Delete`
+    pub operation: &'a str,
+    pub actor_id: &'a str,
+    pub link_name: &'a str,
+    /// The clusters the request is sent to
+    pub clusters: Vec<&'a str>,
+    /// The manifest being applied, `null` for deletes
+    pub object: Option<&'a Value>,
+    pub resource: Resource<'a>,
+}
+
+/// The object a request is for
+#[derive(Debug, Serialize)]
+pub struct Resource<'a> {
+    pub group: &'a str,
+    pub version: &'a str,
+    pub kind: &'a str,
+    pub namespace: Option<&'a str>,
+    pub name: &'a str,
+}
+
+/// A compiled policy along with the rule its deny messages are read from. Cloning is cheap as the
+/// compiled modules and data are shared
+#[derive(Clone)]
+pub struct Policy {
+    compiled: CompiledPolicy,
+    query: String,
+}
+
+impl Policy {
+    /// Loads the policy with the given settings, returning an error if it can't be read or doesn't
+    /// compile
+    pub async fn load(settings: PolicySettings) -> Result<Policy, RpcError> {
+        config::run_blocking("policy", move || Policy::load_blocking(&settings)).await
+    }
+
+    fn load_blocking(settings: &PolicySettings) -> Result<Policy, RpcError> {
+        let mut engine = Engine::new();
+        match &settings.source {
+            PolicySource::File(path) => load_path(&mut engine, Path::new(path))?,
+            PolicySource::Base64(raw) => {
+                let decoded = base64::decode(raw).map_err(|e| {
+                    RpcError::ProviderInit(format!("Policy is not valid base64: {}", e))
+                })?;
+                let rego = String::from_utf8(decoded).map_err(|e| {
+                    RpcError::ProviderInit(format!("Policy is not valid UTF-8: {}", e))
+                })?;
+                engine
+                    .add_policy("policy.rego".to_owned(), rego)
+                    .map_err(|e| policy_error("policy", e))?;
+            }
+        }
+        let compiled = engine
+            .compile_with_entrypoint(&settings.query.as_str().into())
+            .map_err(|e| policy_error(&settings.query, e))?;
+        // Evaluate once so a query that doesn't compile fails the link rather than every request
+        compiled
+            .eval_with_input(regorus::Value::new_object())
+            .map_err(|e| policy_error(&settings.query, e))?;
+        Ok(Policy {
+            compiled,
+            query: settings.query.clone(),
+        })
+    }
+
+    /// Evaluates the policy with the given input, returning the deny messages it produced as
+    /// violations. A policy that fails to evaluate denies the request
+    pub fn evaluate(&self, input: &impl Serialize) -> Vec<PolicyViolation> {
+        // SAFETY: The input types are always representable as JSON
+        let input = serde_json::to_value(input).unwrap();
+        // Every evaluation gets its own engine sharing the compiled policy, as engines keep their input
+        let result = self
+            .compiled
+            .eval_with_input(regorus::Value::from(input))
+            .and_then(|value| {
+                // A rule that isn't defined for the input denies nothing
+                if value == regorus::Value::Undefined {
+                    return Ok(None);
+                }
+                value.to_json_str().map(Some)
+            });
+        let denials = match result {
+            Ok(Some(json)) => serde_json::from_str(&json).unwrap_or(Value::Null),
+            Ok(None) => return Vec::new(),
+            Err(e) => {
+                warn!(error = %e, query = %self.query, "Policy evaluation failed");
+                return vec![
+                    self.violation(String::new(), format!("Policy evaluation failed: {}", e))
+                ];
+            }
+        };
+        match denials {
+            Value::Array(denials) => denials
+                .into_iter()
+                .map(|denial| self.denial(denial))
+                .collect(),
+            Value::Null | Value::Bool(false) => Vec::new(),
+            other => vec![self.denial(other)],
+        }
+    }
+
+    /// Converts a single deny message, which is either a string or an object with a `msg` (or
+    /// `message`) and optionally a `field`
+    fn denial(&self, denial: Value) -> PolicyViolation {
+        match denial {
+            Value::String(message) => self.violation(String::new(), message),
+            Value::Object(obj) => {
+                let text = |key: &str| obj.get(key).and_then(Value::as_str).map(str::to_owned);
+                let message = text("msg")
+                    .or_else(|| text("message"))
+                    .unwrap_or_else(|| Value::Object(obj.clone()).to_string());
+                self.violation(text("field").unwrap_or_default(), message)
+            }
+            other => self.violation(String::new(), other.to_string()),
+        }
+    }
+
+    fn violation(&self, field: String, message: String) -> PolicyViolation {
+        PolicyViolation {
+            policy: self.query.clone(),
+            field,
+            message,
+        }
+    }
+}
+
+/// Loads a single policy file, or every policy and data file in a directory
+fn load_path(engine: &mut Engine, path: &Path) -> Result<(), RpcError> {
+    let display = path.display().to_string();
+    if !path.is_dir() {
+        engine
+            .add_policy_from_file(path)
+            .map_err(|e| policy_error(&display, e))?;
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)
+        .map_err(|e| RpcError::ProviderInit(format!("Unable to read {}: {}", display, e)))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .collect::<Vec<_>>();
+    entries.sort();
+    let mut modules = 0;
+    for file in entries {
+        let file_display = file.display().to_string();
+        if file.extension().and_then(|e| e.to_str()) == Some(REGO_EXTENSION) {
+            engine
+                .add_policy_from_file(&file)
+                .map_err(|e| policy_error(&file_display, e))?;
+            modules += 1;
+        } else if file.file_name().and_then(|n| n.to_str()) == Some(DATA_FILE) {
+            let data = std::fs::read_to_string(&file).map_err(|e| {
+                RpcError::ProviderInit(format!("Unable to read {}: {}", file_display, e))
+            })?;
+            engine
+                .add_data_json(&data)
+                .map_err(|e| policy_error(&file_display, e))?;
+        }
+    }
+    if modules == 0 {
+        return Err(RpcError::ProviderInit(format!(
+            "No .{} files found in {}",
+            REGO_EXTENSION, display
+        )));
+    }
+    Ok(())
+}
+
+fn policy_error(source: &str, e: impl std::fmt::Display) -> RpcError {
+    RpcError::ProviderInit(format!("Invalid policy {}: {}", source, e))
+}
+
+/// Returns a single message describing all of the given denials
+pub fn describe(violations: &[PolicyViolation]) -> String {
+    violations
+        .iter()
+        .map(|v| v.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::test::values;
+
+    use super::*;
+
+    const POLICY: &str = r#"
+package applier
+
+deny contains msg if {
+    input.operation == "Apply"
+    input.object.kind == "Service"
+    input.object.spec.type == "LoadBalancer"
+    msg := sprintf("%s may not create LoadBalancer services", [input.actorId])
+}
+
+deny contains {"msg": "Namespaces can't be deleted", "field": "kind"} if {
+    input.operation == "Delete"
+    input.resource.kind == "Namespace"
+}
+"#;
+
+    fn load(query: &str) -> Policy {
+        Policy::load_blocking(&PolicySettings {
+            source: PolicySource::Base64(base64::encode(POLICY)),
+            query: query.to_owned(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn evaluate() {
+        let policy = load("data.applier.deny");
+        let service = |kind: &str| {
+            serde_json::json!({
+                "operation": "Apply",
+                "actorId": "MACTOR",
+                "object": {"kind": "Service", "spec": {"type": kind}},
+                "resource": {"kind": "Service"},
+            })
+        };
+        assert!(policy.evaluate(&service("ClusterIP")).is_empty());
+        assert_eq!(
+            policy.evaluate(&service("LoadBalancer")),
+            vec![PolicyViolation {
+                policy: "data.applier.deny".to_owned(),
+                field: String::new(),
+                message: "MACTOR may not create LoadBalancer services".to_owned(),
+            }]
+        );
+
+        let delete = serde_json::json!({
+            "operation": "Delete",
+            "object": null,
+            "resource": {"kind": "Namespace"},
+        });
+        let violations = policy.evaluate(&delete);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].field, "kind");
+        assert_eq!(describe(&violations), "Namespaces can't be deleted");
+    }
+
+    #[test]
+    fn invalid_policy() {
+        assert!(Policy::load_blocking(&PolicySettings {
+            source: PolicySource::Base64(base64::encode("package applier\ndeny contains")),
+            query: "data.applier.deny".to_owned(),
+        })
+        .is_err());
+        assert!(Policy::load_blocking(&PolicySettings {
+            source: PolicySource::File("/does/not/exist.rego".to_owned()),
+            query: "data.applier.deny".to_owned(),
+        })
+        .is_err());
+        // A query for a rule the policy doesn't have fails when the link is put
+        assert!(Policy::load_blocking(&PolicySettings {
+            source: PolicySource::Base64(base64::encode(POLICY)),
+            query: "data.applier.missing".to_owned(),
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn settings() {
+        assert!(PolicySettings::from_values(&HashMap::new())
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            PolicySettings::from_values(&values(&[("policy_file", "/etc/policy")])).unwrap(),
+            Some(PolicySettings {
+                source: PolicySource::File("/etc/policy".to_owned()),
+                query: DEFAULT_POLICY_QUERY.to_owned(),
+            })
+        );
+        assert_eq!(
+            PolicySettings::from_values(&values(&[
+                ("policy_b64", "cGFja2FnZQ=="),
+                ("policy_query", "data.security.deny")
+            ]))
+            .unwrap()
+            .unwrap()
+            .query,
+            "data.security.deny"
+        );
+        assert!(PolicySettings::from_values(&values(&[
+            ("policy_file", "/etc/policy"),
+            ("policy_b64", "cGFja2FnZQ==")
+        ]))
+        .is_err());
+    }
+}
@@ -0,0 +1,422 @@
+//! An opt-in per link cache of objects that is kept up to date by watching the API server, so reads
+//! don't need to hit the API server
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use kube::{
+    api::{ApiResource, DynamicObject, ListParams},
+    runtime::{
+        reflector::{store::Writer, ObjectRef, Store},
+        watcher,
+    },
+    Api,
+};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, instrument, warn};
+
+use crate::{cluster::ClusterClient, config::ReadCacheSettings};
+
+/// How long to wait before restarting a watch that failed
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct KindKey {
+    cluster: String,
+    api_version: String,
+    kind: String,
+}
+
+impl KindKey {
+    fn new(cluster: &str, resource: &ApiResource) -> KindKey {
+        KindKey {
+            cluster: cluster.to_owned(),
+            api_version: resource.api_version.clone(),
+            kind: resource.kind.clone(),
+        }
+    }
+}
+
+/// Whether a store is up to date with the API server
+#[derive(Default)]
+struct SyncState {
+    /// Set once the initial list has completed
+    synced: AtomicBool,
+    /// When the watch last failed, if it hasn't recovered since
+    unhealthy_since: Mutex<Option<Instant>>,
+    /// How many times the watch failed and was restarted
+    restarts: AtomicU64,
+}
+
+impl SyncState {
+    /// A store is fresh once it has synced, as long as its watch is healthy or hasn't been failing
+    /// for longer than the max staleness
+    fn is_fresh(&self, max_staleness: Duration) -> bool {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        self.synced.load(Ordering::Relaxed)
+            && self
+                .unhealthy_since
+                .lock()
+                .unwrap()
+                .map(|since| since.elapsed() <= max_staleness)
+                .unwrap_or(true)
+    }
+
+    fn mark_healthy(&self) {
+        self.synced.store(true, Ordering::Relaxed);
+        // SAFETY: The lock is never held across an await or in code that can panic
+        self.unhealthy_since.lock().unwrap().take();
+    }
+
+    /// Whether the store has synced and its watch is currently running without errors
+    fn is_healthy(&self) -> bool {
+        // SAFETY: The lock is never held across an await or in code that can panic
+        self.synced.load(Ordering::Relaxed) && self.unhealthy_since.lock().unwrap().is_none()
+    }
+
+    fn mark_unhealthy(&self) {
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: The lock is never held across an await or in code that can panic
+        self.unhealthy_since
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+    }
+}
+
+struct KindCache {
+    store: Store<DynamicObject>,
+    state: Arc<SyncState>,
+    default_namespace: String,
+    task: JoinHandle<()>,
+}
+
+struct Inner {
+    kinds: HashMap<KindKey, KindCache>,
+    max_staleness: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        for cache in self.kinds.values() {
+            cache.task.abort();
+        }
+    }
+}
+
+/// The state of the watch keeping a single kind on a single cluster up to date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchHealth {
+    pub cluster: String,
+    pub api_version: String,
+    pub kind: String,
+    pub healthy: bool,
+    pub restarts: u64,
+}
+
+/// Caches all objects of the configured kinds on every cluster of a link. Cloning is cheap and the
+/// watches are stopped once the last clone is dropped
+#[derive(Clone)]
+pub struct ReadCache {
+    inner: Arc<Inner>,
+}
+
+impl ReadCache {
+    /// Starts watching each of the configured kinds on each of the given clusters
+    pub fn start(
+        settings: &ReadCacheSettings,
+        clusters: &HashMap<String, ClusterClient>,
+    ) -> ReadCache {
+        let mut kinds = HashMap::new();
+        for (cluster, client) in clusters.iter() {
+            for gvk in settings.kinds.iter() {
+                let resource = ApiResource::from_gvk(gvk);
+                let writer = Writer::new(resource.clone());
+                let state = Arc::new(SyncState::default());
+                let cache = KindCache {
+                    store: writer.as_reader(),
+                    state: state.clone(),
+                    default_namespace: client.default_namespace(),
+                    task: tokio::spawn(watch(
+                        cluster.clone(),
+                        client.clone(),
+                        resource.clone(),
+                        writer,
+                        state,
+                    )),
+                };
+                kinds.insert(KindKey::new(cluster, &resource), cache);
+            }
+        }
+        ReadCache {
+            inner: Arc::new(Inner {
+                kinds,
+                max_staleness: settings.max_staleness,
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Looks up a single object. Returns `None` if the cache can't answer, either because the kind
+    /// isn't cached or the cache is stale, in which case the API server should be asked instead
+    pub fn get(
+        &self,
+        cluster: &str,
+        resource: &ApiResource,
+        namespace: Option<&str>,
+        name: &str,
+    ) -> Option<Option<Arc<DynamicObject>>> {
+        let cache = self.fresh_kind(cluster, resource)?;
+        self.record(true);
+        let obj_ref = ObjectRef::new_with(name, resource.clone());
+        let namespace = namespace.unwrap_or(&cache.default_namespace);
+        // We don't know if the kind is namespaced, so objects without a namespace are checked too
+        Some(
+            cache
+                .store
+                .get(&obj_ref.clone().within(namespace))
+                .or_else(|| cache.store.get(&obj_ref)),
+        )
+    }
+
+    /// Lists all objects in the given namespace (or all namespaces) matching the label selector.
+    /// Returns `None` if the cache can't answer, either because the kind isn't cached, the cache is
+    /// stale or the selector uses set based requirements
+    pub fn list(
+        &self,
+        cluster: &str,
+        resource: &ApiResource,
+        namespace: Option<&str>,
+        label_selector: Option<&str>,
+    ) -> Option<Vec<Arc<DynamicObject>>> {
+        let cache = self.fresh_kind(cluster, resource)?;
+        let selector = match label_selector.map(|raw| (raw, Selector::parse(raw))) {
+            Some((raw, None)) => {
+                debug!(selector = %raw, "Label selector is not supported by the read cache");
+                self.record(false);
+                return None;
+            }
+            Some((_, selector)) => selector,
+            None => None,
+        };
+        self.record(true);
+        let mut objects: Vec<Arc<DynamicObject>> = cache
+            .store
+            .state()
+            .into_iter()
+            .filter(|obj| namespace.is_none() || obj.metadata.namespace.as_deref() == namespace)
+            .filter(|obj| {
+                selector
+                    .as_ref()
+                    .map(|s| s.matches(obj.metadata.labels.as_ref()))
+                    .unwrap_or(true)
+            })
+            .collect();
+        // The store has no order, so we match the order the API server uses
+        objects.sort_by(|a, b| {
+            (&a.metadata.namespace, &a.metadata.name)
+                .cmp(&(&b.metadata.namespace, &b.metadata.name))
+        });
+        Some(objects)
+    }
+
+    /// Returns the number of reads served from the cache and the number that had to go to the API
+    /// server, not counting reads of kinds that aren't cached
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.inner.hits.load(Ordering::Relaxed),
+            self.inner.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Returns the state of every watch, sorted by cluster and kind
+    pub fn watch_health(&self) -> Vec<WatchHealth> {
+        let mut health: Vec<WatchHealth> = self
+            .inner
+            .kinds
+            .iter()
+            .map(|(key, cache)| WatchHealth {
+                cluster: key.cluster.clone(),
+                api_version: key.api_version.clone(),
+                kind: key.kind.clone(),
+                healthy: cache.state.is_healthy(),
+                restarts: cache.state.restarts.load(Ordering::Relaxed),
+            })
+            .collect();
+        health.sort_by(|a, b| {
+            (&a.cluster, &a.api_version, &a.kind).cmp(&(&b.cluster, &b.api_version, &b.kind))
+        });
+        health
+    }
+
+    /// Returns the cache for the given kind if it exists and is fresh. Stale caches count as a miss
+    fn fresh_kind(&self, cluster: &str, resource: &ApiResource) -> Option<&KindCache> {
+        let cache = self.inner.kinds.get(&KindKey::new(cluster, resource))?;
+        if !cache.state.is_fresh(self.inner.max_staleness) {
+            debug!(%cluster, kind = %resource.kind, "Read cache is stale, skipping");
+            self.record(false);
+            return None;
+        }
+        Some(cache)
+    }
+
+    fn record(&self, hit: bool) {
+        let counter = if hit {
+            &self.inner.hits
+        } else {
+            &self.inner.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Keeps the store up to date by watching all objects of the kind. If the watch fails, it is
+/// restarted against the first endpoint of the cluster that can be reached
+#[instrument(level = "debug", skip(client, resource, writer, state), fields(kind = %resource.kind))]
+async fn watch(
+    cluster: String,
+    client: ClusterClient,
+    resource: ApiResource,
+    mut writer: Writer<DynamicObject>,
+    state: Arc<SyncState>,
+) {
+    loop {
+        let api: Api<DynamicObject> = match client.reachable_client().await {
+            Ok(c) => Api::all_with(c, &resource),
+            Err(e) => {
+                warn!(error = %e, "Unable to reach any API server endpoint for read cache watch");
+                state.mark_unhealthy();
+                tokio::time::sleep(RETRY_INTERVAL).await;
+                continue;
+            }
+        };
+        let mut events = watcher(api, ListParams::default()).boxed();
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(event) => {
+                    if matches!(event, watcher::Event::Restarted(_)) {
+                        info!("Read cache synced");
+                    }
+                    writer.apply_watcher_event(&event);
+                    state.mark_healthy();
+                }
+                Err(e) => {
+                    warn!(error = %e, "Read cache watch failed, restarting");
+                    state.mark_unhealthy();
+                    break;
+                }
+            }
+        }
+        tokio::time::sleep(RETRY_INTERVAL).await;
+    }
+}
+
+/// An equality based label selector (e.g. `app=nginx,tier!=frontend,!canary`)
+#[derive(Debug, PartialEq, Eq)]
+struct Selector(Vec<Requirement>);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    Exists(String),
+    DoesNotExist(String),
+}
+
+impl Selector {
+    /// Parses the selector, returning `None` if it uses set based requirements (`in`, `notin`)
+    /// which the cache leaves to the API server
+    fn parse(raw: &str) -> Option<Selector> {
+        if raw.contains('(') {
+            return None;
+        }
+        raw.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|term| {
+                let req = if let Some((k, v)) = term.split_once("!=") {
+                    Requirement::NotEquals(k.trim().to_owned(), v.trim().to_owned())
+                } else if let Some((k, v)) = term.split_once("==") {
+                    Requirement::Equals(k.trim().to_owned(), v.trim().to_owned())
+                } else if let Some((k, v)) = term.split_once('=') {
+                    Requirement::Equals(k.trim().to_owned(), v.trim().to_owned())
+                } else if let Some(k) = term.strip_prefix('!') {
+                    Requirement::DoesNotExist(k.trim().to_owned())
+                } else {
+                    Requirement::Exists(term.to_owned())
+                };
+                match &req {
+                    Requirement::Equals(k, _)
+                    | Requirement::NotEquals(k, _)
+                    | Requirement::Exists(k)
+                    | Requirement::DoesNotExist(k)
+                        if k.is_empty() || k.contains(char::is_whitespace) =>
+                    {
+                        None
+                    }
+                    _ => Some(req),
+                }
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Selector)
+    }
+
+    fn matches(&self, labels: Option<&BTreeMap<String, String>>) -> bool {
+        let get = |k: &String| labels.and_then(|l| l.get(k));
+        self.0.iter().all(|req| match req {
+            Requirement::Equals(k, v) => get(k) == Some(v),
+            Requirement::NotEquals(k, v) => get(k) != Some(v),
+            Requirement::Exists(k) => get(k).is_some(),
+            Requirement::DoesNotExist(k) => get(k).is_none(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn selectors() {
+        let labels: BTreeMap<String, String> = [("app", "nginx"), ("tier", "backend")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let matches = |raw: &str| Selector::parse(raw).unwrap().matches(Some(&labels));
+
+        assert!(matches(""));
+        assert!(matches("app=nginx"));
+        assert!(matches("app == nginx, tier!=frontend"));
+        assert!(matches("tier,!canary"));
+        assert!(!matches("app=apache"));
+        assert!(!matches("canary"));
+        assert!(!matches("!tier"));
+
+        assert!(Selector::parse("tier in (frontend, backend)").is_none());
+        assert!(Selector::parse("tier notin (frontend)").is_none());
+    }
+
+    #[test]
+    fn staleness() {
+        let state = SyncState::default();
+        assert!(!state.is_fresh(Duration::from_secs(30)));
+        state.mark_healthy();
+        assert!(state.is_fresh(Duration::ZERO));
+        state.mark_unhealthy();
+        assert!(state.is_fresh(Duration::from_secs(30)));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(!state.is_fresh(Duration::ZERO));
+        state.mark_healthy();
+        assert!(state.is_fresh(Duration::ZERO));
+    }
+}
@@ -0,0 +1,252 @@
+//! Reloading of clients when a kubeconfig file on disk, or a file it references, changes
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    future::Future,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use kube::config::Kubeconfig;
+use tracing::{debug, info, instrument, warn};
+use wasmbus_rpc::error::RpcError;
+
+use crate::{
+    cluster::{ClusterClient, WeakClusterClient},
+    config::{self, ensure_tls_allowed, load_config, ClientOptions, ClusterSettings, ConfigSource},
+};
+
+/// How often kubeconfig files are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Watches the kubeconfig file of the given cluster (if it was loaded from a file) along with the
+/// certificate, key and token files it references, and rebuilds the client whenever any of them
+/// change, which lets rotated certificates or tokens be picked up. If the new config can't be
+/// loaded, the client keeps using the old config and the reload is tried again on the next check.
+/// The watch stops once the client is dropped
+pub fn watch_kubeconfig(
+    client: &ClusterClient,
+    settings: ClusterSettings,
+    options: ClientOptions,
+    allow_insecure_tls: bool,
+) {
+    let path = match &settings.source {
+        ConfigSource::File(p) => p.clone(),
+        _ => return,
+    };
+    tokio::spawn(watch(
+        client.downgrade(),
+        path,
+        settings,
+        options,
+        allow_insecure_tls,
+    ));
+}
+
+#[instrument(level = "debug", skip(client, settings, options), fields(cluster = %settings.name))]
+async fn watch(
+    client: WeakClusterClient,
+    path: String,
+    settings: ClusterSettings,
+    options: ClientOptions,
+    allow_insecure_tls: bool,
+) {
+    let mut files = KubeconfigFiles::new(path).await;
+    let mut ticker = tokio::time::interval(RELOAD_INTERVAL);
+    // The first tick completes immediately
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let client = match client.upgrade() {
+            Some(c) => c,
+            None => {
+                debug!("Client was dropped, no longer watching kubeconfig");
+                return;
+            }
+        };
+        files
+            .reload_if_changed(|| reload(&client, &settings, &options, allow_insecure_tls))
+            .await;
+    }
+}
+
+/// Builds a new client from the kubeconfig and swaps it in
+async fn reload(
+    client: &ClusterClient,
+    settings: &ClusterSettings,
+    options: &ClientOptions,
+    allow_insecure_tls: bool,
+) -> Result<(), RpcError> {
+    let config = load_config(&settings.source).await?;
+    ensure_tls_allowed(&config, &settings.name, allow_insecure_tls)?;
+    client.replace_with(ClusterClient::connect(settings, config, options).await?);
+    Ok(())
+}
+
+/// A kubeconfig file and the files it references, along with the hash of their contents when the
+/// client was last built from them
+struct KubeconfigFiles {
+    path: String,
+    loaded_hash: Option<u64>,
+}
+
+impl KubeconfigFiles {
+    async fn new(path: String) -> KubeconfigFiles {
+        let loaded_hash = files_hash(&path).await;
+        KubeconfigFiles { path, loaded_hash }
+    }
+
+    /// Calls `reload` if the files changed since they were last loaded. The change is only
+    /// remembered once the reload succeeds, so a failed reload is tried again on the next call
+    async fn reload_if_changed<F, Fut>(&mut self, reload: F)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(), RpcError>>,
+    {
+        // We compare contents rather than modification times as mounted secrets are updated by
+        // swapping symlinks
+        let hash = files_hash(&self.path).await;
+        if hash.is_none() || hash == self.loaded_hash {
+            return;
+        }
+
+        info!("Kubeconfig changed, reloading client");
+        match reload().await {
+            Ok(()) => {
+                self.loaded_hash = hash;
+                info!("Successfully reloaded client");
+            }
+            Err(e) => {
+                warn!(error = %e, "Unable to reload client from changed kubeconfig, continuing to use previous config until the next check")
+            }
+        }
+    }
+}
+
+/// Hashes the kubeconfig along with every file it references. Returns `None` if any of them can't
+/// be read, which happens while a mounted secret is being swapped
+async fn files_hash(path: &str) -> Option<u64> {
+    let path = path.to_owned();
+    config::run_blocking("kubeconfig", move || Ok(files_hash_blocking(&path)))
+        .await
+        .ok()
+        .flatten()
+}
+
+fn files_hash_blocking(path: &str) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    let kubeconfig = match Kubeconfig::read_from(path) {
+        Ok(k) => k,
+        Err(e) => {
+            warn!(%path, error = %e, "Unable to read kubeconfig file");
+            return None;
+        }
+    };
+    let files = std::iter::once(PathBuf::from(path)).chain(referenced_files(path, &kubeconfig));
+    for file in files {
+        match std::fs::read(&file) {
+            Ok(data) => data.hash(&mut hasher),
+            Err(e) => {
+                warn!(path = %file.display(), error = %e, "Unable to read file referenced by kubeconfig");
+                return None;
+            }
+        }
+    }
+    Some(hasher.finish())
+}
+
+/// Returns the certificate, key and token files the kubeconfig references. Relative paths are
+/// relative to the kubeconfig
+fn referenced_files(path: &str, kubeconfig: &Kubeconfig) -> Vec<PathBuf> {
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let clusters = kubeconfig
+        .clusters
+        .iter()
+        .filter_map(|named| named.cluster.certificate_authority.as_ref());
+    let users = kubeconfig.auth_infos.iter().flat_map(|named| {
+        let user = &named.auth_info;
+        [&user.client_certificate, &user.client_key, &user.token_file]
+            .into_iter()
+            .flatten()
+    });
+    clusters.chain(users).map(|file| dir.join(file)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const KUBECONFIG: &str = r#"
+apiVersion: v1
+kind: Config
+clusters:
+  - name: test
+    cluster:
+      server: https://127.0.0.1:6443
+      certificate-authority: ca.pem
+users:
+  - name: test
+    user:
+      tokenFile: token
+contexts:
+  - name: test
+    context:
+      cluster: test
+      user: test
+current-context: test
+"#;
+
+    #[tokio::test]
+    async fn retry_failed_reloads() {
+        let dir = std::env::temp_dir().join(format!("applier-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("kubeconfig");
+        std::fs::write(&path, KUBECONFIG).unwrap();
+        std::fs::write(dir.join("ca.pem"), "ca").unwrap();
+        std::fs::write(dir.join("token"), "first").unwrap();
+
+        let mut files = KubeconfigFiles::new(path.to_string_lossy().into_owned()).await;
+        assert!(files.loaded_hash.is_some());
+        let attempts = AtomicUsize::new(0);
+        let reload = |succeed: bool| {
+            let attempts = &attempts;
+            move || async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                if succeed {
+                    Ok(())
+                } else {
+                    Err(RpcError::ProviderInit("API server unreachable".to_owned()))
+                }
+            }
+        };
+
+        files.reload_if_changed(reload(true)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 0, "Nothing changed yet");
+
+        // Rotating the token file the kubeconfig references is a change
+        std::fs::write(dir.join("token"), "second").unwrap();
+        files.reload_if_changed(reload(false)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        files.reload_if_changed(reload(true)).await;
+        assert_eq!(
+            attempts.load(Ordering::SeqCst),
+            2,
+            "A failed reload must be retried"
+        );
+        files.reload_if_changed(reload(true)).await;
+        assert_eq!(
+            attempts.load(Ordering::SeqCst),
+            2,
+            "A successful reload must not be repeated"
+        );
+
+        // Files that can't be read (e.g. while a secret is being swapped) are not a change
+        std::fs::remove_file(dir.join("ca.pem")).unwrap();
+        files.reload_if_changed(reload(true)).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
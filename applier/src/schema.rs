@@ -0,0 +1,641 @@
+//! Offline validation of manifests against the schemas of built-in kinds (from `k8s-openapi`) and of
+//! custom resources (from the CRDs installed in each cluster), so invalid manifests are rejected with
+//! the path of every offending field before they are sent anywhere
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use k8s_openapi::{
+    api::{
+        admissionregistration, apps, autoscaling, batch, coordination, core, networking, policy,
+        rbac, scheduling, storage,
+    },
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+        CustomResourceDefinition, JSONSchemaProps, JSONSchemaPropsOrArray, JSONSchemaPropsOrBool,
+    },
+    Resource,
+};
+use kube::{
+    api::{ApiResource, ListParams},
+    Api,
+};
+use kubernetes_applier_interface::ValidationError;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tracing::debug;
+use wasmbus_rpc::error::RpcError;
+
+use crate::cluster::ClusterClient;
+
+/// Whether to check manifests against the schema of their kind before sending them: `none` (the
+/// default), `types` to check the types of fields, or `strict` to also reject unknown fields
+const SCHEMA_VALIDATION_KEY: &str = "schema_validation";
+
+/// The maximum number of times a single manifest is checked again after converting a number to a
+/// string or dropping an invalid field. See [`check_typed`]
+const MAX_RETRIES: usize = 64;
+/// How long to wait before fetching the CRDs of a cluster again when an object of a kind we have no
+/// schema for is applied
+const CRD_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+/// Fields of an object that are validated by the API server itself rather than by its schema
+const IMPLICIT_FIELDS: &[&str] = &["apiVersion", "kind", "metadata"];
+
+/// How manifests are checked against the schema of their kind before they are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaValidation {
+    /// Check the types of fields
+    Types,
+    /// Also reject unknown fields
+    Strict,
+}
+
+/// Parses the schema validation setting from the given (lowercased) link values. Returns `None` if
+/// schema validation is disabled
+pub fn schema_validation(
+    values: &HashMap<String, String>,
+) -> Result<Option<SchemaValidation>, RpcError> {
+    match values.get(SCHEMA_VALIDATION_KEY).map(|s| s.trim()) {
+        None | Some("none") => Ok(None),
+        Some("types") => Ok(Some(SchemaValidation::Types)),
+        Some("strict") => Ok(Some(SchemaValidation::Strict)),
+        Some(other) => Err(RpcError::ProviderInit(format!(
+            "Invalid value {} for {}, must be none, types or strict",
+            other, SCHEMA_VALIDATION_KEY
+        ))),
+    }
+}
+
+/// Checks the object against the schema of its built-in kind. Returns `None` if the object isn't of
+/// a built-in kind we have a schema for. Unknown fields are only reported if `strict` is set
+pub fn validate_builtin(object: &Value, strict: bool) -> Option<Vec<ValidationError>> {
+    let api_version = object.get("apiVersion").and_then(Value::as_str)?;
+    let kind = object.get("kind").and_then(Value::as_str)?;
+
+    macro_rules! builtin_kinds {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                if api_version == <$ty as Resource>::API_VERSION && kind == <$ty as Resource>::KIND {
+                    return Some(check_typed::<$ty>(object, strict));
+                }
+            )*
+        };
+    }
+    builtin_kinds!(
+        core::v1::ConfigMap,
+        core::v1::Endpoints,
+        core::v1::LimitRange,
+        core::v1::Namespace,
+        core::v1::PersistentVolume,
+        core::v1::PersistentVolumeClaim,
+        core::v1::Pod,
+        core::v1::PodTemplate,
+        core::v1::ReplicationController,
+        core::v1::ResourceQuota,
+        core::v1::Secret,
+        core::v1::Service,
+        core::v1::ServiceAccount,
+        apps::v1::DaemonSet,
+        apps::v1::Deployment,
+        apps::v1::ReplicaSet,
+        apps::v1::StatefulSet,
+        batch::v1::CronJob,
+        batch::v1::Job,
+        batch::v1beta1::CronJob,
+        autoscaling::v1::HorizontalPodAutoscaler,
+        autoscaling::v2beta2::HorizontalPodAutoscaler,
+        networking::v1::Ingress,
+        networking::v1::IngressClass,
+        networking::v1::NetworkPolicy,
+        policy::v1::PodDisruptionBudget,
+        policy::v1beta1::PodDisruptionBudget,
+        policy::v1beta1::PodSecurityPolicy,
+        rbac::v1::ClusterRole,
+        rbac::v1::ClusterRoleBinding,
+        rbac::v1::Role,
+        rbac::v1::RoleBinding,
+        storage::v1::CSIDriver,
+        storage::v1::StorageClass,
+        scheduling::v1::PriorityClass,
+        coordination::v1::Lease,
+        admissionregistration::v1::MutatingWebhookConfiguration,
+        admissionregistration::v1::ValidatingWebhookConfiguration,
+        CustomResourceDefinition,
+    );
+    None
+}
+
+/// Checks the object by deserializing it as the typed kind. Unknown fields are found by serializing
+/// the typed object again and looking for fields that were dropped along the way.
+///
+/// Deserializing stops at the first error, so each field with the wrong type is dropped after it
+/// is reported and the check is retried to find the rest. A missing required field or a list item
+/// of the wrong type can't be dropped that way, so checking stops there (without looking for
+/// unknown fields).
+///
+/// Kubernetes accepts numbers for quantities (e.g. `cpu: 1`), which are strings in the typed
+/// structs, so numbers found where a string is expected are converted and the check is retried
+fn check_typed<T: DeserializeOwned + Serialize>(
+    object: &Value,
+    strict: bool,
+) -> Vec<ValidationError> {
+    let mut value = object.clone();
+    let mut errors = Vec::new();
+    for _ in 0..MAX_RETRIES {
+        let err = match serde_path_to_error::deserialize::<_, T>(value.clone()) {
+            Ok(typed) => {
+                if strict {
+                    // SAFETY: All k8s-openapi types are representable as JSON
+                    let known = serde_json::to_value(&typed).unwrap();
+                    unknown_fields(&value, &known, "", &mut errors);
+                }
+                return errors;
+            }
+            Err(e) => e,
+        };
+        let path = err.path().clone();
+        let message = err.into_inner().to_string();
+        if message.ends_with("expected a string") {
+            if let Some(target) = value_at(&mut value, path.iter()).filter(|v| v.is_number()) {
+                *target = Value::String(target.to_string());
+                continue;
+            }
+        }
+        let field = match path.to_string().as_str() {
+            "." => String::new(),
+            other => other.to_owned(),
+        };
+        if let Some(rest) = message.strip_prefix("missing field `") {
+            let field = join(&field, rest.trim_end_matches('`'));
+            // A required field dropped for having the wrong type was already reported
+            if !errors.iter().any(|e| e.field == field) {
+                errors.push(error(&field, "missing required field"));
+            }
+            return errors;
+        }
+        errors.push(error(&field, &message));
+        if !remove_at(&mut value, &path) {
+            return errors;
+        }
+    }
+    errors
+}
+
+/// Returns the value at the given path, if it exists
+fn value_at<'a, 'p>(
+    value: &'a mut Value,
+    mut path: impl Iterator<Item = &'p serde_path_to_error::Segment>,
+) -> Option<&'a mut Value> {
+    path.try_fold(value, |value, segment| match segment {
+        serde_path_to_error::Segment::Seq { index } => value.get_mut(*index),
+        serde_path_to_error::Segment::Map { key } => value.get_mut(key.as_str()),
+        _ => None,
+    })
+}
+
+/// Removes the field at the given path from its object. Returns false if the path doesn't end in a
+/// field that exists
+fn remove_at(value: &mut Value, path: &serde_path_to_error::Path) -> bool {
+    let segments: Vec<_> = path.iter().collect();
+    match segments.split_last() {
+        Some((serde_path_to_error::Segment::Map { key }, parent)) => {
+            value_at(value, parent.iter().copied())
+                .and_then(Value::as_object_mut)
+                .and_then(|object| object.remove(key.as_str()))
+                .is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Reports every field of the original object that isn't in the known object
+fn unknown_fields(original: &Value, known: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    match (original, known) {
+        (Value::Object(original), Value::Object(known)) => {
+            for (key, value) in original.iter() {
+                let field = join(path, key);
+                match known.get(key) {
+                    Some(known) => unknown_fields(value, known, &field, errors),
+                    None if value.is_null() => {}
+                    None => errors.push(error(&field, "unknown field")),
+                }
+            }
+        }
+        (Value::Array(original), Value::Array(known)) => {
+            for (i, (value, known)) in original.iter().zip(known.iter()).enumerate() {
+                unknown_fields(value, known, &format!("{}[{}]", path, i), errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Checks the object against the OpenAPI schema of its custom resource. This covers the structural
+/// parts of the schema (types, required fields, enums, bounds and unknown fields) but not patterns,
+/// formats or `allOf`/`anyOf`/`oneOf`/`not`, which are left to the API server
+pub fn validate_custom(
+    object: &Value,
+    schema: &JSONSchemaProps,
+    strict: bool,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    check_schema(object, schema, "", true, strict, &mut errors);
+    errors
+}
+
+fn check_schema(
+    value: &Value,
+    schema: &JSONSchemaProps,
+    path: &str,
+    embedded: bool,
+    strict: bool,
+    errors: &mut Vec<ValidationError>,
+) {
+    if value.is_null() {
+        return;
+    }
+    if schema.x_kubernetes_int_or_string == Some(true) {
+        if !(value.is_i64() || value.is_u64() || value.is_string()) {
+            errors.push(error(path, "expected an integer or string"));
+        }
+        return;
+    }
+    if let Some(ty) = schema.type_.as_deref() {
+        if !type_matches(ty, value) {
+            errors.push(error(path, &format!("expected {}", ty)));
+            return;
+        }
+    }
+    if let Some(allowed) = schema.enum_.as_ref() {
+        if !allowed.iter().any(|a| &a.0 == value) {
+            let allowed: Vec<String> = allowed.iter().map(|a| a.0.to_string()).collect();
+            errors.push(error(
+                path,
+                &format!("must be one of {}", allowed.join(", ")),
+            ));
+        }
+    }
+
+    match value {
+        Value::String(s) => {
+            let len = s.chars().count() as i64;
+            if matches!(schema.min_length, Some(min) if len < min) {
+                errors.push(error(path, "string is too short"));
+            }
+            if matches!(schema.max_length, Some(max) if len > max) {
+                errors.push(error(path, "string is too long"));
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.minimum {
+                if n < min || (schema.exclusive_minimum == Some(true) && n == min) {
+                    errors.push(error(path, &format!("must be at least {}", min)));
+                }
+            }
+            if let Some(max) = schema.maximum {
+                if n > max || (schema.exclusive_maximum == Some(true) && n == max) {
+                    errors.push(error(path, &format!("must be at most {}", max)));
+                }
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as i64;
+            if matches!(schema.min_items, Some(min) if len < min) {
+                errors.push(error(path, "too few items"));
+            }
+            if matches!(schema.max_items, Some(max) if len > max) {
+                errors.push(error(path, "too many items"));
+            }
+            for (i, item) in items.iter().enumerate() {
+                let item_schema = match schema.items.as_ref() {
+                    Some(JSONSchemaPropsOrArray::Schema(s)) => Some(s.as_ref()),
+                    Some(JSONSchemaPropsOrArray::Schemas(list)) => list.get(i),
+                    None => None,
+                };
+                if let Some(item_schema) = item_schema {
+                    let embedded = item_schema.x_kubernetes_embedded_resource == Some(true);
+                    let field = format!("{}[{}]", path, i);
+                    check_schema(item, item_schema, &field, embedded, strict, errors);
+                }
+            }
+        }
+        Value::Object(map) => {
+            for required in schema.required.iter().flatten() {
+                if !map.contains_key(required) {
+                    errors.push(error(&join(path, required), "missing required field"));
+                }
+            }
+            for (key, value) in map.iter() {
+                if embedded && IMPLICIT_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+                let field = join(path, key);
+                if let Some(prop) = schema.properties.as_ref().and_then(|p| p.get(key)) {
+                    let embedded = prop.x_kubernetes_embedded_resource == Some(true);
+                    check_schema(value, prop, &field, embedded, strict, errors);
+                    continue;
+                }
+                match schema.additional_properties.as_ref() {
+                    Some(JSONSchemaPropsOrBool::Schema(s)) => {
+                        check_schema(value, s, &field, false, strict, errors)
+                    }
+                    Some(JSONSchemaPropsOrBool::Bool(true)) => {}
+                    _ if schema.x_kubernetes_preserve_unknown_fields == Some(true) => {}
+                    _ if strict => errors.push(error(&field, "unknown field")),
+                    _ => {}
+                }
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+fn type_matches(ty: &str, value: &Value) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value.as_f64().map(|f| f.fract() == 0.0) == Some(true)
+        }
+        "number" => value.is_number(),
+        _ => true,
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn error(field: &str, reason: &str) -> ValidationError {
+    ValidationError {
+        field: field.to_owned(),
+        reason: reason.to_owned(),
+    }
+}
+
+/// Returns a single message describing all of the given validation errors
+pub fn describe(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.reason))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// The schemas of the custom resources installed in a cluster
+struct ClusterSchemas {
+    fetched: Instant,
+    /// Schemas by group, version and kind
+    schemas: HashMap<(String, String, String), Arc<JSONSchemaProps>>,
+}
+
+/// The CRD schemas of each cluster on a link. These are fetched once and only fetched again (at
+/// most once per refresh interval) when an object of a kind we have no schema for is applied, e.g.
+/// after a new CRD was installed. Cloning is cheap and all clones share the same schemas
+#[derive(Clone, Default)]
+pub struct CrdSchemas {
+    clusters: Arc<Mutex<HashMap<String, ClusterSchemas>>>,
+}
+
+impl CrdSchemas {
+    /// Returns the schema of the given custom resource in the given cluster, if it can be found
+    pub async fn get(
+        &self,
+        cluster: &str,
+        client: &ClusterClient,
+        resource: &ApiResource,
+    ) -> Option<Arc<JSONSchemaProps>> {
+        let key = (
+            resource.group.clone(),
+            resource.version.clone(),
+            resource.kind.clone(),
+        );
+        {
+            // SAFETY: The lock is never held across an await or in code that can panic
+            let clusters = self.clusters.lock().unwrap();
+            if let Some(cached) = clusters.get(cluster) {
+                if let Some(schema) = cached.schemas.get(&key) {
+                    return Some(schema.clone());
+                }
+                if cached.fetched.elapsed() < CRD_REFRESH_INTERVAL {
+                    return None;
+                }
+            }
+        }
+
+        debug!(%cluster, "Fetching custom resource schemas");
+        let resp = client
+            .call(|c| async move {
+                Api::<CustomResourceDefinition>::all(c)
+                    .list(&ListParams::default())
+                    .await
+            })
+            .await;
+        let mut schemas = HashMap::new();
+        match resp {
+            Ok(crds) => {
+                for crd in crds.items {
+                    let spec = crd.spec;
+                    for version in spec.versions {
+                        if let Some(schema) = version.schema.and_then(|s| s.open_api_v3_schema) {
+                            schemas.insert(
+                                (spec.group.clone(), version.name, spec.names.kind.clone()),
+                                Arc::new(schema),
+                            );
+                        }
+                    }
+                }
+            }
+            // The provider may not be allowed to list CRDs or the cluster may be unreachable, in
+            // which case custom resources are left to the API server
+            Err(e) => debug!(%cluster, error = %e, "Unable to fetch custom resource schemas"),
+        }
+
+        // SAFETY: The lock is never held across an await or in code that can panic
+        let mut clusters = self.clusters.lock().unwrap();
+        let cached = clusters
+            .entry(cluster.to_owned())
+            .or_insert_with(|| ClusterSchemas {
+                fetched: Instant::now(),
+                schemas: HashMap::new(),
+            });
+        cached.fetched = Instant::now();
+        if !schemas.is_empty() {
+            cached.schemas = schemas;
+        }
+        cached.schemas.get(&key).cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::test::values;
+
+    use super::*;
+
+    fn parse(manifest: &str) -> Value {
+        serde_yaml::from_str(manifest).unwrap()
+    }
+
+    const DEPLOYMENT: &str = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: nginx
+  creationTimestamp: null
+spec:
+  replicas: 3
+  selector:
+    matchLabels:
+      app: nginx
+  template:
+    metadata:
+      labels:
+        app: nginx
+    spec:
+      containers:
+        - name: nginx
+          image: nginx:1.23
+          resources:
+            limits:
+              cpu: 1
+              memory: 128Mi
+"#;
+
+    #[test]
+    fn builtin_kinds() {
+        let valid = parse(DEPLOYMENT);
+        assert_eq!(validate_builtin(&valid, true), Some(Vec::new()));
+
+        let typo = parse(&DEPLOYMENT.replace("replicas: 3", "replcas: 3"));
+        assert_eq!(validate_builtin(&typo, false), Some(Vec::new()));
+        assert_eq!(
+            validate_builtin(&typo, true).unwrap(),
+            vec![error("spec.replcas", "unknown field")]
+        );
+
+        let wrong_type = parse(&DEPLOYMENT.replace("replicas: 3", "replicas: three"));
+        let errors = validate_builtin(&wrong_type, false).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "spec.replicas");
+
+        let nested = parse(&DEPLOYMENT.replace("image: nginx:1.23", "image: [nginx]"));
+        let errors = validate_builtin(&nested, false).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "spec.template.spec.containers[0].image");
+
+        let several = parse(
+            &DEPLOYMENT
+                .replace("replicas: 3", "replicas: three\n  replcas: 3")
+                .replace("image: nginx:1.23", "image: [nginx]"),
+        );
+        let fields =
+            |errors: Vec<ValidationError>| errors.into_iter().map(|e| e.field).collect::<Vec<_>>();
+        assert_eq!(
+            fields(validate_builtin(&several, true).unwrap()),
+            vec![
+                "spec.replicas",
+                "spec.template.spec.containers[0].image",
+                "spec.replcas"
+            ]
+        );
+
+        let required = parse(&DEPLOYMENT.replace(
+            "name: nginx\n          image",
+            "name: [nginx]\n          image",
+        ));
+        assert_eq!(
+            validate_builtin(&required, false).unwrap(),
+            vec![error(
+                "spec.template.spec.containers[0].name",
+                "invalid type: sequence, expected a string"
+            )]
+        );
+
+        let custom = parse("apiVersion: example.com/v1\nkind: Widget\nmetadata:\n  name: foo\n");
+        assert!(validate_builtin(&custom, true).is_none());
+    }
+
+    #[test]
+    fn custom_resources() {
+        let schema: JSONSchemaProps = serde_json::from_value(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "spec": {
+                    "type": "object",
+                    "required": ["size"],
+                    "properties": {
+                        "size": { "type": "integer", "minimum": 1 },
+                        "color": { "type": "string", "enum": ["red", "blue"] },
+                        "port": { "x-kubernetes-int-or-string": true },
+                        "extra": { "type": "object", "x-kubernetes-preserve-unknown-fields": true },
+                        "tags": { "type": "array", "items": { "type": "string" } }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let valid = parse(
+            r#"
+apiVersion: example.com/v1
+kind: Widget
+metadata:
+  name: foo
+spec:
+  size: 2
+  color: red
+  port: http
+  extra:
+    anything: goes
+  tags: [a, b]
+"#,
+        );
+        assert!(validate_custom(&valid, &schema, true).is_empty());
+
+        let invalid = parse(
+            r#"
+apiVersion: example.com/v1
+kind: Widget
+metadata:
+  name: foo
+spec:
+  color: green
+  port: 1.5
+  tags: [a, 1]
+  colour: red
+"#,
+        );
+        assert_eq!(
+            validate_custom(&invalid, &schema, false),
+            vec![
+                error("spec.size", "missing required field"),
+                error("spec.color", "must be one of \"red\", \"blue\""),
+                error("spec.port", "expected an integer or string"),
+                error("spec.tags[1]", "expected string"),
+            ]
+        );
+        assert!(validate_custom(&invalid, &schema, true)
+            .contains(&error("spec.colour", "unknown field")));
+    }
+
+    #[test]
+    fn schema_validation_setting() {
+        assert_eq!(schema_validation(&HashMap::new()).unwrap(), None);
+        assert_eq!(
+            schema_validation(&values(&[("schema_validation", "strict")])).unwrap(),
+            Some(SchemaValidation::Strict)
+        );
+        assert!(schema_validation(&values(&[("schema_validation", "all")])).is_err());
+    }
+}
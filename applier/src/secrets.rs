@@ -0,0 +1,263 @@
+//! Decryption of Secret values encrypted with age, so actors never have to send the plaintext over
+//! the lattice. Encrypted values are written as `ENC[age,<base64 ciphertext>]` in the `data` or
+//! `stringData` of a Secret and only decrypted right before the object is sent to the API server
+
+use std::{collections::HashMap, io::Read, str::FromStr, sync::Arc};
+
+use age::x25519::Identity;
+use kube::api::DynamicObject;
+use serde_json::Value;
+use wasmbus_rpc::error::RpcError;
+
+use crate::config;
+
+/// An age identity (`AGE-SECRET-KEY-1...`) used to decrypt encrypted Secret values
+const AGE_IDENTITY_KEY: &str = "age_identity";
+/// The path to a file of age identities, one per line, used in the same way as `age_identity`
+const AGE_IDENTITY_FILE_KEY: &str = "age_identity_file";
+
+/// The start of an encrypted value
+const ENCRYPTED_PREFIX: &str = "ENC[age,";
+/// The end of an encrypted value
+const ENCRYPTED_SUFFIX: &str = "]";
+/// The fields of a Secret that may contain encrypted values
+const ENCRYPTED_FIELDS: &[&str] = &["data", "stringData"];
+
+/// Where the age identities encrypted Secret values are decrypted with come from
+#[derive(Clone, PartialEq, Eq)]
+pub struct DecryptionSettings {
+    pub identity: Option<String>,
+    pub identity_file: Option<String>,
+}
+
+impl DecryptionSettings {
+    /// Parses the decryption settings from the given (lowercased) link values. Returns `None` if no
+    /// identity is set
+    pub fn from_values(
+        values: &HashMap<String, String>,
+    ) -> Result<Option<DecryptionSettings>, RpcError> {
+        let settings = DecryptionSettings {
+            identity: values.get(AGE_IDENTITY_KEY).map(|s| s.trim().to_owned()),
+            identity_file: values
+                .get(AGE_IDENTITY_FILE_KEY)
+                .map(|s| s.trim().to_owned()),
+        };
+        if settings.identity.is_none() && settings.identity_file.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(settings))
+    }
+}
+
+// The identity is a private key, so it is never printed
+impl std::fmt::Debug for DecryptionSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecryptionSettings")
+            .field("identity", &self.identity.as_ref().map(|_| "<redacted>"))
+            .field("identity_file", &self.identity_file)
+            .finish()
+    }
+}
+
+/// Decrypts values with a link's age identities
+#[derive(Clone)]
+pub struct Decrypter {
+    identities: Arc<Vec<Identity>>,
+}
+
+impl Decrypter {
+    /// Loads the identities from the given settings, returning an error if any of them are invalid
+    pub async fn load(settings: DecryptionSettings) -> Result<Decrypter, RpcError> {
+        config::run_blocking("age identities", move || {
+            Decrypter::load_blocking(&settings)
+        })
+        .await
+    }
+
+    fn load_blocking(settings: &DecryptionSettings) -> Result<Decrypter, RpcError> {
+        let mut identities = Vec::new();
+        if let Some(identity) = settings.identity.as_deref() {
+            identities.push(parse_identity(identity)?);
+        }
+        if let Some(path) = settings.identity_file.as_deref() {
+            let raw = std::fs::read_to_string(path).map_err(|e| {
+                RpcError::ProviderInit(format!("Unable to read age identity file {}: {}", path, e))
+            })?;
+            // Identity files can hold several identities along with comments
+            for line in raw.lines().map(str::trim) {
+                if !line.is_empty() && !line.starts_with('#') {
+                    identities.push(parse_identity(line)?);
+                }
+            }
+        }
+        if identities.is_empty() {
+            return Err(RpcError::ProviderInit(
+                "No age identities found to decrypt secrets with".to_owned(),
+            ));
+        }
+        Ok(Decrypter {
+            identities: Arc::new(identities),
+        })
+    }
+
+    /// Returns a copy of the object with all encrypted values decrypted. Errors never contain any
+    /// of the decrypted data
+    pub fn decrypt(&self, object: &DynamicObject) -> Result<DynamicObject, String> {
+        let mut object = object.clone();
+        for field in ENCRYPTED_FIELDS {
+            let values = match object.data.get_mut(field).and_then(Value::as_object_mut) {
+                Some(values) => values,
+                None => continue,
+            };
+            for (key, value) in values.iter_mut() {
+                let ciphertext = match ciphertext(value) {
+                    Some(ciphertext) => ciphertext,
+                    None => continue,
+                };
+                let plaintext = self
+                    .decrypt_value(ciphertext)
+                    .map_err(|e| format!("Unable to decrypt {}.{}: {}", field, key, e))?;
+                *value = if *field == "data" {
+                    // Values of data are base64 encoded bytes
+                    Value::String(base64::encode(plaintext))
+                } else {
+                    Value::String(String::from_utf8(plaintext).map_err(|_| {
+                        format!("Decrypted value of {}.{} is not valid UTF-8", field, key)
+                    })?)
+                };
+            }
+        }
+        Ok(object)
+    }
+
+    fn decrypt_value(&self, encoded: &str) -> Result<Vec<u8>, String> {
+        let ciphertext = base64::decode(encoded).map_err(|e| format!("invalid base64: {}", e))?;
+        let decryptor =
+            age::Decryptor::new_buffered(ciphertext.as_slice()).map_err(|e| e.to_string())?;
+        let mut reader = decryptor
+            .decrypt(self.identities.iter().map(|i| i as &dyn age::Identity))
+            .map_err(|e| e.to_string())?;
+        let mut plaintext = Vec::new();
+        reader
+            .read_to_end(&mut plaintext)
+            .map_err(|e| e.to_string())?;
+        Ok(plaintext)
+    }
+}
+
+/// Returns whether the object is a Secret with encrypted values
+pub fn is_encrypted(object: &DynamicObject) -> bool {
+    is_secret(object)
+        && ENCRYPTED_FIELDS.iter().any(|field| {
+            object
+                .data
+                .get(field)
+                .and_then(Value::as_object)
+                .map(|values| values.values().any(|v| ciphertext(v).is_some()))
+                .unwrap_or_default()
+        })
+}
+
+/// Replaces the encrypted values of a Secret (in its JSON form) with empty strings, so checks that
+/// expect plain values (e.g. base64 in `data`) can be run on it
+pub fn redact(object: &mut Value) {
+    if object.get("kind").and_then(Value::as_str) != Some("Secret") {
+        return;
+    }
+    for field in ENCRYPTED_FIELDS {
+        let values = match object.get_mut(field).and_then(Value::as_object_mut) {
+            Some(values) => values,
+            None => continue,
+        };
+        for value in values.values_mut() {
+            if ciphertext(value).is_some() {
+                *value = Value::String(String::new());
+            }
+        }
+    }
+}
+
+fn is_secret(object: &DynamicObject) -> bool {
+    object
+        .types
+        .as_ref()
+        .map(|t| t.api_version == "v1" && t.kind == "Secret")
+        .unwrap_or_default()
+}
+
+/// Returns the base64 encoded ciphertext of an encrypted value
+fn ciphertext(value: &Value) -> Option<&str> {
+    value
+        .as_str()?
+        .strip_prefix(ENCRYPTED_PREFIX)?
+        .strip_suffix(ENCRYPTED_SUFFIX)
+}
+
+fn parse_identity(raw: &str) -> Result<Identity, RpcError> {
+    // Identities are case insensitive bech32, but the age crate only accepts them in uppercase
+    Identity::from_str(&raw.trim().to_uppercase())
+        .map_err(|e| RpcError::ProviderInit(format!("Invalid age identity: {}", e)))
+}
+
+#[cfg(test)]
+mod test {
+    use age::secrecy::ExposeSecret;
+
+    use super::*;
+
+    fn encrypt(identity: &Identity, plaintext: &str) -> String {
+        let ciphertext = age::encrypt(&identity.to_public(), plaintext.as_bytes()).unwrap();
+        format!(
+            "{}{}{}",
+            ENCRYPTED_PREFIX,
+            base64::encode(ciphertext),
+            ENCRYPTED_SUFFIX
+        )
+    }
+
+    fn secret(identity: &Identity) -> DynamicObject {
+        serde_json::from_value(serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "Secret",
+            "metadata": {"name": "creds"},
+            "data": {
+                "password": encrypt(identity, "hunter2"),
+                "username": base64::encode("admin"),
+            },
+            "stringData": {"token": encrypt(identity, "abc123")},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn decrypt_secrets() {
+        let identity = Identity::generate();
+        let decrypter = Decrypter::load_blocking(&DecryptionSettings {
+            identity: Some(identity.to_string().expose_secret().to_lowercase()),
+            identity_file: None,
+        })
+        .unwrap();
+        let object = secret(&identity);
+        assert!(is_encrypted(&object));
+
+        let decrypted = decrypter.decrypt(&object).unwrap();
+        assert!(!is_encrypted(&decrypted));
+        assert_eq!(
+            decrypted.data["data"]["password"],
+            base64::encode("hunter2")
+        );
+        assert_eq!(decrypted.data["data"]["username"], base64::encode("admin"));
+        assert_eq!(decrypted.data["stringData"]["token"], "abc123");
+
+        // Values encrypted for someone else can't be decrypted
+        let err = decrypter
+            .decrypt(&secret(&Identity::generate()))
+            .unwrap_err();
+        assert!(err.starts_with("Unable to decrypt data.password"));
+
+        let mut value = serde_json::to_value(&object).unwrap();
+        redact(&mut value);
+        assert_eq!(value["data"]["password"], "");
+        assert_eq!(value["stringData"]["token"], "");
+    }
+}
@@ -0,0 +1,138 @@
+//! Provider wide settings given by the host when the provider is started
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use serde::Deserialize;
+use wasmbus_rpc::{core::HostData, error::RpcError};
+
+/// Settings that apply to the whole provider rather than a single link. These are passed as JSON
+/// in the provider's config (e.g. with `wash ctl start provider --config-json`) so they can only be
+/// changed by whoever runs the host
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProviderSettings {
+    /// Whether links may use kubeconfigs that disable TLS certificate verification
+    /// (`insecure-skip-tls-verify`). Off by default as it makes it trivial to intercept the
+    /// credentials sent to the API server
+    pub allow_insecure_tls: bool,
+    /// Whether every link must verify manifest signatures, regardless of its `verify_signatures`
+    /// setting. Links without any `trusted_signers` can't be put when this is set
+    pub require_signed_manifests: bool,
+    /// Where to write the audit log of every apply and delete, if anywhere
+    pub audit_log: Option<AuditSettings>,
+    /// The port to serve Prometheus metrics on at `/metrics`. Metrics are not served if unset
+    pub metrics_port: Option<u16>,
+    /// The address the metrics server listens on, only the local host by default
+    pub metrics_address: IpAddr,
+    /// The OTLP (HTTP) endpoint to export trace spans to, e.g. `http://localhost:4318`. Spans are
+    /// not exported if unset, unless wasmbus-rpc's `OTEL_*` environment variables are set
+    pub otlp_endpoint: Option<String>,
+}
+
+/// Where the audit log is written and when it is rotated
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AuditSettings {
+    /// The path of the current log file. Rotated files get a `.1`, `.2`, etc. suffix
+    pub path: String,
+    /// The size in bytes after which the log is rotated
+    #[serde(default = "default_audit_max_bytes")]
+    pub max_bytes: u64,
+    /// How many rotated files are kept
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: u32,
+}
+
+impl Default for ProviderSettings {
+    fn default() -> ProviderSettings {
+        ProviderSettings {
+            allow_insecure_tls: false,
+            require_signed_manifests: false,
+            audit_log: None,
+            metrics_port: None,
+            metrics_address: default_metrics_address(),
+            otlp_endpoint: None,
+        }
+    }
+}
+
+fn default_metrics_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
+fn default_audit_max_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_audit_max_files() -> u32 {
+    5
+}
+
+impl ProviderSettings {
+    /// Parses the settings from the config JSON in the given host data, using the defaults if no
+    /// config was given
+    pub fn from_host_data(host_data: &HostData) -> Result<ProviderSettings, RpcError> {
+        match host_data.config_json.as_deref().map(str::trim) {
+            None | Some("") => Ok(ProviderSettings::default()),
+            Some(raw) => serde_json::from_str(raw).map_err(|e| {
+                RpcError::ProviderInit(format!("Invalid provider config JSON: {}", e))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_settings() {
+        let mut host_data = HostData::default();
+        assert!(
+            !ProviderSettings::from_host_data(&host_data)
+                .unwrap()
+                .allow_insecure_tls
+        );
+
+        host_data.config_json = Some(r#"{"allow_insecure_tls": true}"#.to_owned());
+        assert!(
+            ProviderSettings::from_host_data(&host_data)
+                .unwrap()
+                .allow_insecure_tls
+        );
+
+        host_data.config_json = Some(r#"{"require_signed_manifests": true}"#.to_owned());
+        let settings = ProviderSettings::from_host_data(&host_data).unwrap();
+        assert!(settings.require_signed_manifests && !settings.allow_insecure_tls);
+
+        host_data.config_json =
+            Some(r#"{"audit_log": {"path": "/var/log/applier.log"}}"#.to_owned());
+        assert_eq!(
+            ProviderSettings::from_host_data(&host_data)
+                .unwrap()
+                .audit_log
+                .unwrap(),
+            AuditSettings {
+                path: "/var/log/applier.log".to_owned(),
+                max_bytes: default_audit_max_bytes(),
+                max_files: default_audit_max_files(),
+            }
+        );
+
+        host_data.config_json = Some(r#"{"metrics_port": 9090}"#.to_owned());
+        let settings = ProviderSettings::from_host_data(&host_data).unwrap();
+        assert_eq!(settings.metrics_port, Some(9090));
+        assert_eq!(settings.metrics_address, default_metrics_address());
+
+        host_data.config_json = Some(r#"{"otlp_endpoint": "http://localhost:4318"}"#.to_owned());
+        assert_eq!(
+            ProviderSettings::from_host_data(&host_data)
+                .unwrap()
+                .otlp_endpoint
+                .as_deref(),
+            Some("http://localhost:4318")
+        );
+
+        host_data.config_json = Some("not json".to_owned());
+        assert!(ProviderSettings::from_host_data(&host_data).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! Verification of the nkey signatures actors send along with the manifests they apply
+
+use std::collections::HashMap;
+
+use kubernetes_applier_interface::ManifestSignature;
+use nkeys::KeyPair;
+use wasmbus_rpc::error::RpcError;
+
+use crate::{
+    config::parse_value,
+    error::{ErrorKind, RequestError},
+};
+
+/// A comma delimited list of the nkey public keys allowed to sign manifests
+const TRUSTED_SIGNERS_KEY: &str = "trusted_signers";
+/// Whether applies must carry a valid signature from one of the `trusted_signers`
+const VERIFY_SIGNATURES_KEY: &str = "verify_signatures";
+
+/// Parses whether manifest signatures should be verified from the given (lowercased) link values
+pub fn verify_signatures(values: &HashMap<String, String>) -> Result<bool, RpcError> {
+    Ok(parse_value::<bool>(values, VERIFY_SIGNATURES_KEY)?.unwrap_or_default())
+}
+
+/// Parses the public keys of the trusted manifest signers from the given (lowercased) link values
+pub fn trusted_signers(values: &HashMap<String, String>) -> Result<Vec<String>, RpcError> {
+    let raw = match values.get(TRUSTED_SIGNERS_KEY) {
+        Some(raw) => raw,
+        None => return Ok(Vec::new()),
+    };
+    raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|key| {
+            KeyPair::from_public_key(key).map_err(|e| {
+                RpcError::ProviderInit(format!(
+                    "Invalid public key {} in {}: {}",
+                    key, TRUSTED_SIGNERS_KEY, e
+                ))
+            })?;
+            Ok(key.to_owned())
+        })
+        .collect()
+}
+
+/// Checks that the manifest was signed by one of the trusted signers
+pub fn verify(
+    trusted_signers: &[String],
+    manifest: &[u8],
+    signature: Option<&ManifestSignature>,
+) -> Result<(), RequestError> {
+    let signature = signature.ok_or_else(|| {
+        RequestError::new(
+            ErrorKind::Untrusted,
+            "Manifest is not signed and the link requires signed manifests",
+        )
+    })?;
+    if !trusted_signers.contains(&signature.signer_key) {
+        return Err(RequestError::new(
+            ErrorKind::Untrusted,
+            format!(
+                "Manifest is signed by {}, which is not a trusted signer",
+                signature.signer_key
+            ),
+        ));
+    }
+    // SAFETY: Trusted signers are checked to be valid public keys when the link is put
+    let key = KeyPair::from_public_key(&signature.signer_key).unwrap();
+    key.verify(manifest, &signature.signature).map_err(|e| {
+        RequestError::new(
+            ErrorKind::Untrusted,
+            format!("Invalid manifest signature: {}", e),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::test::values;
+
+    use super::*;
+
+    const MANIFEST: &[u8] = b"apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: foo\n";
+
+    fn sign(key: &KeyPair, manifest: &[u8]) -> ManifestSignature {
+        ManifestSignature {
+            signature: key.sign(manifest).unwrap(),
+            signer_key: key.public_key(),
+        }
+    }
+
+    #[test]
+    fn verify_signatures() {
+        let trusted = KeyPair::new_user();
+        let other = KeyPair::new_user();
+        let signers = vec![trusted.public_key()];
+
+        assert!(verify(&signers, MANIFEST, Some(&sign(&trusted, MANIFEST))).is_ok());
+
+        let untrusted = [
+            verify(&signers, MANIFEST, None),
+            verify(&signers, MANIFEST, Some(&sign(&other, MANIFEST))),
+            // A signature from a trusted key over a different manifest
+            verify(&signers, MANIFEST, Some(&sign(&trusted, b"kind: Secret"))),
+            // A trusted key claiming another key's signature
+            verify(
+                &signers,
+                MANIFEST,
+                Some(&ManifestSignature {
+                    signer_key: trusted.public_key(),
+                    ..sign(&other, MANIFEST)
+                }),
+            ),
+        ];
+        for result in untrusted {
+            assert_eq!(result.unwrap_err().kind, ErrorKind::Untrusted);
+        }
+    }
+
+    #[test]
+    fn settings() {
+        let key = KeyPair::new_user().public_key();
+        let vals = values(&[
+            ("trusted_signers", &format!("{}, ", key)),
+            ("verify_signatures", "true"),
+        ]);
+        assert_eq!(trusted_signers(&vals).unwrap(), vec![key]);
+        assert!(super::verify_signatures(&vals).unwrap());
+        assert!(!super::verify_signatures(&HashMap::new()).unwrap());
+        assert!(trusted_signers(&values(&[("trusted_signers", "not a key")])).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! OpenTelemetry tracing of requests. wasmbus-rpc attaches the trace context in the headers of each
+//! invocation to its `rpc` span (see [`wasmbus_rpc::otel`]), which the span of every request is
+//! nested in. Every call to the API server gets a child span and spans are exported over OTLP by
+//! the tracing setup in wasmbus-rpc
+
+use std::{future::Future, time::Duration};
+
+use http::{Request, Response};
+use kube::core::ApiResource;
+use tracing::{Instrument, Span};
+
+/// The environment variable that turns on exporting spans in wasmbus-rpc
+const TRACES_EXPORTER_ENV: &str = "OTEL_TRACES_EXPORTER";
+/// The environment variable wasmbus-rpc reads the OTLP endpoint from
+const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Exports spans over OTLP (HTTP) to the given endpoint. This only sets the environment variables
+/// wasmbus-rpc reads when it sets up tracing, so it must be called before the provider is run and
+/// while the process is still single threaded
+pub fn export_to(endpoint: &str) {
+    std::env::set_var(TRACES_EXPORTER_ENV, "otlp");
+    std::env::set_var(OTLP_ENDPOINT_ENV, endpoint);
+}
+
+/// Returns the span for a single call to the API server
+pub fn request_span(
+    operation: &str,
+    cluster: &str,
+    resource: &ApiResource,
+    namespace: Option<&str>,
+    name: Option<&str>,
+) -> Span {
+    tracing::debug_span!(
+        "kube_request",
+        otel.name = %format!("{} {}", operation, resource.kind),
+        otel.kind = "client",
+        otel.status_code = tracing::field::Empty,
+        otel.status_message = tracing::field::Empty,
+        http.status_code = tracing::field::Empty,
+        k8s.operation = operation,
+        k8s.cluster = cluster,
+        k8s.group = %resource.group,
+        k8s.version = %resource.version,
+        k8s.kind = %resource.kind,
+        k8s.namespace = namespace,
+        k8s.name = name,
+    )
+}
+
+/// Returns the span for an HTTP request to the API server, in the same form as the spans of the
+/// clients kube builds, for the clients we build ourselves
+pub fn http_span(req: &Request<hyper::Body>) -> Span {
+    tracing::debug_span!(
+        "HTTP",
+        http.method = %req.method(),
+        http.url = %req.uri(),
+        http.status_code = tracing::field::Empty,
+        otel.name = "HTTP",
+        otel.kind = "client",
+        otel.status_code = tracing::field::Empty,
+    )
+}
+
+/// Records the status of the response to an HTTP request on its span
+pub fn record_response(resp: &Response<hyper::Body>, _latency: Duration, span: &Span) {
+    let status = resp.status();
+    span.record("http.status_code", &status.as_u16());
+    if status.is_client_error() || status.is_server_error() {
+        span.record("otel.status_code", &"ERROR");
+    }
+}
+
+/// Runs the call to the API server in the given span, recording how it went
+pub async fn traced<T, F>(span: Span, call: F) -> Result<T, kube::Error>
+where
+    F: Future<Output = Result<T, kube::Error>>,
+{
+    let result = call.instrument(span.clone()).await;
+    match &result {
+        Ok(_) => {
+            span.record("otel.status_code", &"OK");
+        }
+        Err(e) => {
+            if let kube::Error::Api(resp) = e {
+                span.record("http.status_code", &resp.code);
+            }
+            span.record("otel.status_code", &"ERROR");
+            span.record("otel.status_message", &tracing::field::display(e));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use http::{HeaderMap, HeaderValue};
+    use opentelemetry::{
+        propagation::TextMapPropagator,
+        sdk::propagation::TraceContextPropagator,
+        trace::{SpanId, TraceContextExt, TraceId},
+    };
+    use tracing_opentelemetry::{OpenTelemetrySpanExt, OtelData};
+    use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, Registry};
+    use wasmbus_rpc::otel::OtelHeaderExtractor;
+
+    use super::*;
+
+    const TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+    const SPAN_ID: &str = "b7ad6b7169203331";
+
+    /// Returns the trace and span id of the parent of the given span
+    fn parent(span: &Span) -> (TraceId, SpanId) {
+        span.with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<Registry>().unwrap();
+            let span = registry.span(id).unwrap();
+            let extensions = span.extensions();
+            let parent = extensions.get::<OtelData>().unwrap().parent_cx.span();
+            let parent = parent.span_context();
+            (parent.trace_id(), parent.span_id())
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn continue_invocation_trace() {
+        let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // This is what wasmbus-rpc does with the headers of an invocation
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_str(&format!("00-{}-{}-01", TRACE_ID, SPAN_ID)).unwrap(),
+        );
+        let rpc = tracing::debug_span!("rpc");
+        rpc.set_parent(TraceContextPropagator::new().extract(&OtelHeaderExtractor::new(&headers)));
+        assert_eq!(
+            parent(&rpc),
+            (
+                TraceId::from_hex(TRACE_ID).unwrap(),
+                SpanId::from_hex(SPAN_ID).unwrap()
+            )
+        );
+
+        let resource = ApiResource::erase::<k8s_openapi::api::core::v1::ConfigMap>(&());
+        let request = rpc.in_scope(|| {
+            let apply = tracing::debug_span!("apply");
+            apply.in_scope(|| request_span("Apply", "default", &resource, None, Some("test")))
+        });
+        traced(request.clone(), async { Ok(()) }).await.unwrap();
+        assert_eq!(parent(&request).0, TraceId::from_hex(TRACE_ID).unwrap());
+        assert_eq!(
+            request.context().span().span_context().trace_id(),
+            TraceId::from_hex(TRACE_ID).unwrap()
+        );
+    }
+}
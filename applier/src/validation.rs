@@ -0,0 +1,153 @@
+//! The server-side field validation a link asks for and the extraction of the fields it rejected
+//! from API server errors
+
+use std::{collections::HashMap, str::FromStr};
+
+use kube::core::params::ValidationDirective;
+use kubernetes_applier_interface::ValidationError;
+use wasmbus_rpc::error::RpcError;
+
+use crate::config::parse_value;
+
+/// How the API server handles unknown or duplicate fields in applied manifests: `ignore`, `warn` or
+/// `strict`. Defaults to the API server's default
+const FIELD_VALIDATION_KEY: &str = "field_validation";
+
+/// Reasons given for fields in `strict decoding error: unknown field "spec.foo"` messages
+const QUOTED_REASONS: &[&str] = &["unknown field", "duplicate field"];
+/// The reason given for fields in `.spec.foo: field not declared in schema` messages, which is what
+/// server-side apply returns
+const NOT_DECLARED: &str = "field not declared in schema";
+
+/// How the API server should handle unknown or duplicate fields in applied manifests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldValidation {
+    /// Silently drop the fields
+    Ignore,
+    /// Drop the fields and return a warning for each
+    Warn,
+    /// Reject the manifest
+    Strict,
+}
+
+impl FieldValidation {
+    pub fn directive(self) -> ValidationDirective {
+        match self {
+            FieldValidation::Ignore => ValidationDirective::Ignore,
+            FieldValidation::Warn => ValidationDirective::Warn,
+            FieldValidation::Strict => ValidationDirective::Strict,
+        }
+    }
+}
+
+impl FromStr for FieldValidation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ignore" => Ok(FieldValidation::Ignore),
+            "warn" => Ok(FieldValidation::Warn),
+            "strict" => Ok(FieldValidation::Strict),
+            _ => Err("must be Ignore, Warn or Strict".to_owned()),
+        }
+    }
+}
+
+/// Parses the field validation setting from the given (lowercased) link values, if it is set
+pub fn field_validation(
+    values: &HashMap<String, String>,
+) -> Result<Option<FieldValidation>, RpcError> {
+    parse_value(values, FIELD_VALIDATION_KEY)
+}
+
+/// Returns the fields rejected by field validation in the given error, if it is a validation error
+pub fn validation_errors(e: &kube::Error) -> Vec<ValidationError> {
+    match e {
+        kube::Error::Api(resp) if resp.code == 400 || resp.code == 422 => parse(&resp.message),
+        _ => Vec::new(),
+    }
+}
+
+fn parse(message: &str) -> Vec<ValidationError> {
+    // Errors are collected with their position so they are returned in the order the server gave
+    let mut found: Vec<(usize, ValidationError)> = Vec::new();
+
+    for reason in QUOTED_REASONS {
+        let pattern = format!("{} \"", reason);
+        for (idx, _) in message.match_indices(&pattern) {
+            let start = idx + pattern.len();
+            if let Some(len) = message[start..].find('"') {
+                found.push((idx, error(&message[start..start + len], reason)));
+            }
+        }
+    }
+
+    let pattern = format!(": {}", NOT_DECLARED);
+    for (idx, _) in message.match_indices(&pattern) {
+        let field = message[..idx]
+            .rsplit(|c: char| c.is_whitespace() || c == ',')
+            .next()
+            .unwrap_or_default();
+        if !field.is_empty() {
+            found.push((idx, error(field, NOT_DECLARED)));
+        }
+    }
+
+    found.sort_by_key(|(idx, _)| *idx);
+    let mut errors: Vec<ValidationError> = Vec::with_capacity(found.len());
+    for (_, e) in found {
+        if !errors.contains(&e) {
+            errors.push(e);
+        }
+    }
+    errors
+}
+
+fn error(field: &str, reason: &str) -> ValidationError {
+    ValidationError {
+        field: field.trim_start_matches('.').to_owned(),
+        reason: reason.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::test::values;
+
+    use super::*;
+
+    #[test]
+    fn parse_messages() {
+        assert_eq!(
+            parse(
+                r#"strict decoding error: unknown field "spec.replcas", duplicate field "metadata.name""#
+            ),
+            vec![
+                error("spec.replcas", "unknown field"),
+                error("metadata.name", "duplicate field")
+            ]
+        );
+        assert_eq!(
+            parse("failed to create typed patch object (default/nginx; apps/v1, Kind=Deployment): .spec.replcas: field not declared in schema\n.spec.template.spec.foo: field not declared in schema"),
+            vec![
+                error("spec.replcas", NOT_DECLARED),
+                error("spec.template.spec.foo", NOT_DECLARED)
+            ]
+        );
+        assert!(parse("deployments.apps \"nginx\" is forbidden").is_empty());
+    }
+
+    #[test]
+    fn field_validation_setting() {
+        assert_eq!(field_validation(&HashMap::new()).unwrap(), None);
+        assert_eq!(
+            field_validation(&values(&[("field_validation", "Strict")])).unwrap(),
+            Some(FieldValidation::Strict)
+        );
+        assert_eq!(
+            field_validation(&values(&[("field_validation", "warn")])).unwrap(),
+            Some(FieldValidation::Warn)
+        );
+        assert!(field_validation(&values(&[("field_validation", "loose")])).is_err());
+    }
+}
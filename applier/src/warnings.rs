@@ -0,0 +1,99 @@
+//! Collection of the warnings the API server returns in `Warning` headers (e.g. for deprecated API
+//! versions or unknown fields) so they can be passed on to actors
+
+use std::{cell::RefCell, future::Future};
+
+use http::{header::WARNING, Response};
+use tower::util::MapResponseLayer;
+use tracing::debug;
+
+tokio::task_local! {
+    /// The warnings returned to the request currently being handled
+    static WARNINGS: RefCell<Vec<String>>;
+}
+
+/// Returns a layer recording the warnings in every response for the request being handled.
+/// Responses to anything outside of [`collect`] (e.g. read cache watches) are ignored
+pub fn layer<B>() -> MapResponseLayer<fn(Response<B>) -> Response<B>> {
+    MapResponseLayer::new(record::<B>)
+}
+
+fn record<B>(resp: Response<B>) -> Response<B> {
+    let warnings = resp
+        .headers()
+        .get_all(WARNING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .map(warning_text);
+    let _ = WARNINGS.try_with(|collected| {
+        let mut collected = collected.borrow_mut();
+        for warning in warnings {
+            debug!(%warning, "API server returned warning");
+            // Retries against another endpoint can return the same warning twice
+            if !collected.contains(&warning) {
+                collected.push(warning);
+            }
+        }
+    });
+    resp
+}
+
+/// Runs the future, returning its output along with all warnings the API server returned to the
+/// requests it sent
+pub async fn collect<F: Future>(fut: F) -> (F::Output, Vec<String>) {
+    WARNINGS
+        .scope(RefCell::new(Vec::new()), async move {
+            let output = fut.await;
+            (output, WARNINGS.with(|w| w.take()))
+        })
+        .await
+}
+
+/// Extracts the text from a warning header value (`<code> <agent> "<text>"`), falling back to the
+/// whole value if it isn't in that format
+fn warning_text(value: &str) -> String {
+    let quoted = value
+        .split_once('"')
+        .and_then(|(_, rest)| rest.strip_suffix('"'));
+    match quoted {
+        Some(text) => text.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.trim().to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_warning() {
+        assert_eq!(
+            warning_text(r#"299 - "policy/v1beta1 PodSecurityPolicy is deprecated in v1.21+""#),
+            "policy/v1beta1 PodSecurityPolicy is deprecated in v1.21+"
+        );
+        assert_eq!(
+            warning_text(r#"299 - "unknown field \"spec.foo\"""#),
+            r#"unknown field "spec.foo""#
+        );
+        assert_eq!(warning_text("something else"), "something else");
+    }
+
+    #[tokio::test]
+    async fn collect_warnings() {
+        let resp = || {
+            Response::builder()
+                .header(WARNING, r#"299 - "first""#)
+                .header(WARNING, r#"299 - "second""#)
+                .body(())
+                .unwrap()
+        };
+        let ((), warnings) = collect(async {
+            record(resp());
+            record(resp());
+        })
+        .await;
+        assert_eq!(warnings, vec!["first", "second"]);
+        // Nothing is recorded outside of collect
+        record(resp());
+    }
+}
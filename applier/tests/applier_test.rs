@@ -20,7 +20,8 @@ async fn run_all() {
         create_update_delete_happy_path,
         invalid_create,
         invalid_update,
-        nonexistent_delete
+        nonexistent_delete,
+        unknown_cluster
     );
     print_test_results(&res);
 
@@ -94,9 +95,19 @@ async fn create_update_delete_happy_path(_opt: &TestOptions) -> RpcResult<()> {
     };
 
     let resp = client
-        .apply(&ctx, &VALID_MANIFEST.as_bytes().to_vec())
+        .apply(
+            &ctx,
+            &ApplyRequest {
+                manifest: VALID_MANIFEST.as_bytes().to_vec(),
+                ..Default::default()
+            },
+        )
         .await?;
     assert!(resp.succeeded, "Create should have succeeded");
+    assert!(
+        resp.cluster_results.unwrap_or_default()[0].created,
+        "Result should show the object was created"
+    );
 
     // Validate service exists
     api.get(svc_name)
@@ -104,9 +115,19 @@ async fn create_update_delete_happy_path(_opt: &TestOptions) -> RpcResult<()> {
         .unwrap_or_else(|_| panic!("Service {} does not exist", svc_name));
 
     let resp = client
-        .apply(&ctx, &VALID_MANIFEST_WITH_LABELS.as_bytes().to_vec())
+        .apply(
+            &ctx,
+            &ApplyRequest {
+                manifest: VALID_MANIFEST_WITH_LABELS.as_bytes().to_vec(),
+                ..Default::default()
+            },
+        )
         .await?;
     assert!(resp.succeeded, "Update should have succeeded");
+    assert!(
+        !resp.cluster_results.unwrap_or_default()[0].created,
+        "Result should show the object was updated"
+    );
 
     let svc = api
         .get(svc_name)
@@ -123,6 +144,72 @@ async fn create_update_delete_happy_path(_opt: &TestOptions) -> RpcResult<()> {
         "Label value should be set correctly"
     );
 
+    // Applying the same manifest again should be skipped unless forced
+    let resp = client
+        .apply(
+            &ctx,
+            &ApplyRequest {
+                manifest: VALID_MANIFEST_WITH_LABELS.as_bytes().to_vec(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.succeeded, "Identical apply should have succeeded");
+    assert!(
+        resp.cluster_results.unwrap_or_default()[0].unchanged,
+        "Identical apply should have been skipped"
+    );
+
+    let resp = client
+        .apply(
+            &ctx,
+            &ApplyRequest {
+                manifest: VALID_MANIFEST_WITH_LABELS.as_bytes().to_vec(),
+                force: true,
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.succeeded, "Forced apply should have succeeded");
+    assert!(
+        !resp.cluster_results.unwrap_or_default()[0].unchanged,
+        "Forced apply should have been sent to the server"
+    );
+
+    let resp = client
+        .get(
+            &ctx,
+            &GetRequest {
+                group: String::new(),
+                kind: "Service".into(),
+                version: "v1".into(),
+                name: svc_name.into(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.succeeded, "Get should have succeeded");
+    assert!(resp.found, "Service should have been found");
+    let svc: Service =
+        serde_json::from_slice(&resp.object.expect("Object should be returned")).unwrap();
+    assert_eq!(svc.metadata.name.as_deref(), Some(svc_name));
+
+    let resp = client
+        .list(
+            &ctx,
+            &ListRequest {
+                group: String::new(),
+                kind: "Service".into(),
+                version: "v1".into(),
+                namespace: Some("default".into()),
+                label_selector: Some("foo=happy".into()),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(resp.succeeded, "List should have succeeded");
+    assert_eq!(resp.objects.len(), 1, "Service should have been listed");
+
     let resp = client
         .delete(
             &ctx,
@@ -139,6 +226,24 @@ async fn create_update_delete_happy_path(_opt: &TestOptions) -> RpcResult<()> {
     if api.get(svc_name).await.is_ok() {
         panic!("Service {} should be deleted", svc_name)
     }
+
+    let resp = client
+        .get(
+            &ctx,
+            &GetRequest {
+                group: String::new(),
+                kind: "Service".into(),
+                version: "v1".into(),
+                name: svc_name.into(),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert!(
+        resp.succeeded,
+        "Get of a missing object should have succeeded"
+    );
+    assert!(!resp.found, "Deleted service should not have been found");
     Ok(())
 }
 
@@ -174,7 +279,13 @@ async fn invalid_create(_opt: &TestOptions) -> RpcResult<()> {
     };
 
     let resp = client
-        .apply(&ctx, &INVALID_MANIFEST.as_bytes().to_vec())
+        .apply(
+            &ctx,
+            &ApplyRequest {
+                manifest: INVALID_MANIFEST.as_bytes().to_vec(),
+                ..Default::default()
+            },
+        )
         .await?;
     assert!(!resp.succeeded, "Create should not have succeeded");
     assert!(resp.error.is_some(), "Error message should be set");
@@ -224,7 +335,13 @@ async fn invalid_update(_opt: &TestOptions) -> RpcResult<()> {
     };
 
     let resp = client
-        .apply(&ctx, &INVALID_UPDATE_MANIFEST.as_bytes().to_vec())
+        .apply(
+            &ctx,
+            &ApplyRequest {
+                manifest: INVALID_UPDATE_MANIFEST.as_bytes().to_vec(),
+                ..Default::default()
+            },
+        )
         .await?;
     assert!(!resp.succeeded, "Update should not have succeeded");
     assert!(resp.error.is_some(), "Error message should be set");
@@ -264,3 +381,48 @@ async fn nonexistent_delete(_opt: &TestOptions) -> RpcResult<()> {
 
     Ok(())
 }
+
+/// Test that requests for a cluster that isn't registered on the link are rejected
+async fn unknown_cluster(_opt: &TestOptions) -> RpcResult<()> {
+    let prov = test_provider().await;
+
+    // The test scaffolding doesn't wait for an ack from the link, so wait for a bit
+    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+
+    let actor_id = prov.origin().public_key();
+    // create client and ctx
+    let client = KubernetesApplierSender::via(prov);
+    let ctx = Context {
+        actor: Some(actor_id),
+        ..Default::default()
+    };
+
+    let resp = client
+        .apply(
+            &ctx,
+            &ApplyRequest {
+                manifest: VALID_MANIFEST.as_bytes().to_vec(),
+                cluster: Some("not-a-cluster".into()),
+                ..Default::default()
+            },
+        )
+        .await;
+    check!(resp.is_err())?;
+
+    let resp = client
+        .delete(
+            &ctx,
+            &DeleteRequest {
+                group: String::new(),
+                kind: "Service".into(),
+                version: "v1".into(),
+                name: "foo-applier-test-noexist".into(),
+                cluster: Some("not-a-cluster".into()),
+                ..Default::default()
+            },
+        )
+        .await;
+    check!(resp.is_err())?;
+
+    Ok(())
+}
@@ -22,73 +22,2198 @@ use wasmbus_rpc::{
 #[allow(dead_code)]
 pub const SMITHY_VERSION: &str = "1.0";
 
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ApplyRequest {
+    /// If set to true, the object will be sent to all clusters registered on the link. `cluster` is
+    /// ignored when this is set
+    #[serde(rename = "allClusters")]
+    #[serde(default)]
+    pub all_clusters: bool,
+    /// The name of the cluster to send the object to. If not specified, the default cluster for the
+    /// link is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+    /// How the API server should handle unknown or duplicate fields in the manifest: `Ignore`, `Warn`
+    /// or `Strict`. Overrides the link's `field_validation` setting if set
+    #[serde(rename = "fieldValidation")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_validation: Option<String>,
+    /// The provider skips applies of manifests identical to the last one it applied for the same
+    /// object. Set this to true to send the apply to the API server anyway, e.g. to revert changes
+    /// made to the object by someone else
+    #[serde(default)]
+    pub force: bool,
+    /// The manifest of the object to create or update. This can be either YAML or JSON
+    #[serde(with = "serde_bytes")]
+    #[serde(default)]
+    pub manifest: Vec<u8>,
+    /// A signature over the exact bytes of `manifest`. Required if the link verifies signatures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ManifestSignature>,
+    /// The maximum time in milliseconds the provider may spend on this request, including time spent
+    /// waiting for rate limits. If the link has a `request_timeout`, the shorter of the two is used
+    #[serde(rename = "timeoutMs")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+}
+
+// Encode ApplyRequest as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_apply_request<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ApplyRequest,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(7)?;
+    e.str("allClusters")?;
+    e.bool(val.all_clusters)?;
+    if let Some(val) = val.cluster.as_ref() {
+        e.str("cluster")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.field_validation.as_ref() {
+        e.str("fieldValidation")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("force")?;
+    e.bool(val.force)?;
+    e.str("manifest")?;
+    e.bytes(&val.manifest)?;
+    if let Some(val) = val.signature.as_ref() {
+        e.str("signature")?;
+        encode_manifest_signature(e, val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.timeout_ms.as_ref() {
+        e.str("timeoutMs")?;
+        e.u32(*val)?;
+    } else {
+        e.null()?;
+    }
+    Ok(())
+}
+
+// Decode ApplyRequest from cbor input stream
+#[doc(hidden)]
+pub fn decode_apply_request(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<ApplyRequest, RpcError> {
+    let __result = {
+        let mut all_clusters: Option<bool> = None;
+        let mut cluster: Option<Option<String>> = Some(None);
+        let mut field_validation: Option<Option<String>> = Some(None);
+        let mut force: Option<bool> = None;
+        let mut manifest: Option<Vec<u8>> = None;
+        let mut signature: Option<Option<ManifestSignature>> = Some(None);
+        let mut timeout_ms: Option<Option<u32>> = Some(None);
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct ApplyRequest, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => all_clusters = Some(d.bool()?),
+                    1 => {
+                        cluster = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    2 => {
+                        field_validation = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    3 => force = Some(d.bool()?),
+                    4 => manifest = Some(d.bytes()?.to_vec()),
+                    5 => {
+                        signature = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some( decode_manifest_signature(d).map_err(|e| format!("decoding 'com.cosmonic.kubernetesapplier#ManifestSignature': {}", e))? ))
+                        }
+                    }
+                    6 => {
+                        timeout_ms = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.u32()?))
+                        }
+                    }
+
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "allClusters" => all_clusters = Some(d.bool()?),
+                    "cluster" => {
+                        cluster = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "fieldValidation" => {
+                        field_validation = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "force" => force = Some(d.bool()?),
+                    "manifest" => manifest = Some(d.bytes()?.to_vec()),
+                    "signature" => {
+                        signature = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some( decode_manifest_signature(d).map_err(|e| format!("decoding 'com.cosmonic.kubernetesapplier#ManifestSignature': {}", e))? ))
+                        }
+                    }
+                    "timeoutMs" => {
+                        timeout_ms = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.u32()?))
+                        }
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        }
+        ApplyRequest {
+            all_clusters: if let Some(__x) = all_clusters {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ApplyRequest.all_clusters (#0)".to_string(),
+                ));
+            },
+            cluster: cluster.unwrap(),
+            field_validation: field_validation.unwrap(),
+
+            force: if let Some(__x) = force {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ApplyRequest.force (#3)".to_string(),
+                ));
+            },
+
+            manifest: if let Some(__x) = manifest {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ApplyRequest.manifest (#4)".to_string(),
+                ));
+            },
+            signature: signature.unwrap(),
+            timeout_ms: timeout_ms.unwrap(),
+        }
+    };
+    Ok(__result)
+}
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ClusterResult {
+    /// The name of the cluster as registered on the link
+    #[serde(default)]
+    pub cluster: String,
+    /// For applies, whether the object was newly created rather than updated. Always false for
+    /// deletes
+    #[serde(default)]
+    pub created: bool,
+    /// An optional message describing the error if one occurred
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// A machine readable kind for the error, if the operation failed for a reason callers may want
+    /// to handle specifically. See `ErrorKind` for the possible values
+    #[serde(rename = "errorKind")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    /// The resourceVersion of the object after the operation, if the API server returned it. For
+    /// skipped applies this is the version from when the manifest was last applied
+    #[serde(rename = "resourceVersion")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_version: Option<String>,
+    /// Whether or not the operation succeeded on this cluster
+    #[serde(default)]
+    pub succeeded: bool,
+    /// For applies, whether the object was skipped because the same manifest was already applied by
+    /// this link
+    #[serde(default)]
+    pub unchanged: bool,
+    /// The fields rejected by strict field validation
+    #[serde(rename = "validationErrors")]
+    pub validation_errors: ValidationErrors,
+    /// Warnings returned by the API server, e.g. for deprecated API versions or unknown fields
+    pub warnings: WarningList,
+}
+
+// Encode ClusterResult as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_cluster_result<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ClusterResult,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(9)?;
+    e.str("cluster")?;
+    e.str(&val.cluster)?;
+    e.str("created")?;
+    e.bool(val.created)?;
+    if let Some(val) = val.error.as_ref() {
+        e.str("error")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.error_kind.as_ref() {
+        e.str("errorKind")?;
+        encode_error_kind(e, val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.resource_version.as_ref() {
+        e.str("resourceVersion")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("succeeded")?;
+    e.bool(val.succeeded)?;
+    e.str("unchanged")?;
+    e.bool(val.unchanged)?;
+    e.str("validationErrors")?;
+    encode_validation_errors(e, &val.validation_errors)?;
+    e.str("warnings")?;
+    encode_warning_list(e, &val.warnings)?;
+    Ok(())
+}
+
+// Decode ClusterResult from cbor input stream
+#[doc(hidden)]
+pub fn decode_cluster_result(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<ClusterResult, RpcError> {
+    let __result = {
+        let mut cluster: Option<String> = None;
+        let mut created: Option<bool> = None;
+        let mut error: Option<Option<String>> = Some(None);
+        let mut error_kind: Option<Option<ErrorKind>> = Some(None);
+        let mut resource_version: Option<Option<String>> = Some(None);
+        let mut succeeded: Option<bool> = None;
+        let mut unchanged: Option<bool> = None;
+        let mut validation_errors: Option<ValidationErrors> = None;
+        let mut warnings: Option<WarningList> = None;
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct ClusterResult, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => cluster = Some(d.str()?.to_string()),
+                    1 => created = Some(d.bool()?),
+                    2 => {
+                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    3 => {
+                        error_kind = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_error_kind(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ErrorKind': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    4 => {
+                        resource_version = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    5 => succeeded = Some(d.bool()?),
+                    6 => unchanged = Some(d.bool()?),
+                    7 => {
+                        validation_errors = Some(decode_validation_errors(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#ValidationErrors': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    8 => {
+                        warnings = Some(decode_warning_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#WarningList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "cluster" => cluster = Some(d.str()?.to_string()),
+                    "created" => created = Some(d.bool()?),
+                    "error" => {
+                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "errorKind" => {
+                        error_kind = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_error_kind(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ErrorKind': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    "resourceVersion" => {
+                        resource_version = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "succeeded" => succeeded = Some(d.bool()?),
+                    "unchanged" => unchanged = Some(d.bool()?),
+                    "validationErrors" => {
+                        validation_errors = Some(decode_validation_errors(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#ValidationErrors': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    "warnings" => {
+                        warnings = Some(decode_warning_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#WarningList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        }
+        ClusterResult {
+            cluster: if let Some(__x) = cluster {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ClusterResult.cluster (#0)".to_string(),
+                ));
+            },
+
+            created: if let Some(__x) = created {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ClusterResult.created (#1)".to_string(),
+                ));
+            },
+            error: error.unwrap(),
+            error_kind: error_kind.unwrap(),
+            resource_version: resource_version.unwrap(),
+
+            succeeded: if let Some(__x) = succeeded {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ClusterResult.succeeded (#5)".to_string(),
+                ));
+            },
+
+            unchanged: if let Some(__x) = unchanged {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ClusterResult.unchanged (#6)".to_string(),
+                ));
+            },
+
+            validation_errors: if let Some(__x) = validation_errors {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ClusterResult.validation_errors (#7)".to_string(),
+                ));
+            },
+
+            warnings: if let Some(__x) = warnings {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ClusterResult.warnings (#8)".to_string(),
+                ));
+            },
+        }
+    };
+    Ok(__result)
+}
+/// A list of per cluster results
+pub type ClusterResults = Vec<ClusterResult>;
+
+// Encode ClusterResults as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_cluster_results<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ClusterResults,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.array(val.len() as u64)?;
+    for item in val.iter() {
+        encode_cluster_result(e, item)?;
+    }
+    Ok(())
+}
+
+// Decode ClusterResults from cbor input stream
+#[doc(hidden)]
+pub fn decode_cluster_results(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<ClusterResults, RpcError> {
+    let __result = {
+        if let Some(n) = d.array()? {
+            let mut arr: Vec<ClusterResult> = Vec::with_capacity(n as usize);
+            for _ in 0..(n as usize) {
+                arr.push(decode_cluster_result(d).map_err(|e| {
+                    format!(
+                        "decoding 'com.cosmonic.kubernetesapplier#ClusterResult': {}",
+                        e
+                    )
+                })?)
+            }
+            arr
+        } else {
+            // indefinite array
+            let mut arr: Vec<ClusterResult> = Vec::new();
+            loop {
+                match d.datatype() {
+                    Err(_) => break,
+                    Ok(wasmbus_rpc::cbor::Type::Break) => break,
+                    Ok(_) => arr.push(decode_cluster_result(d).map_err(|e| {
+                        format!(
+                            "decoding 'com.cosmonic.kubernetesapplier#ClusterResult': {}",
+                            e
+                        )
+                    })?),
+                }
+            }
+            arr
+        }
+    };
+    Ok(__result)
+}
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct DeleteRequest {
+    /// If set to true, the object will be deleted from all clusters registered on the link. `cluster`
+    /// is ignored when this is set
+    #[serde(rename = "allClusters")]
+    #[serde(default)]
+    pub all_clusters: bool,
+    /// The name of the cluster to delete the object from. If not specified, the default cluster for
+    /// the link is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
     /// The group of the object you are deleting (e.g. "networking.k8s.io"). This will be an empty
     /// string if part of `core`
     #[serde(default)]
-    pub group: String,
-    /// The kind of the object you are deleting (e.g. Pod)
+    pub group: String,
+    /// The kind of the object you are deleting (e.g. Pod)
+    #[serde(default)]
+    pub kind: String,
+    /// The name of the object you are deleting
+    #[serde(default)]
+    pub name: String,
+    /// The namespace where the object you want to delete is located. If not specified, the default
+    /// namespace for the context should be used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// The maximum time in milliseconds the provider may spend on this request, including time spent
+    /// waiting for rate limits. If the link has a `request_timeout`, the shorter of the two is used
+    #[serde(rename = "timeoutMs")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+    /// The API version of the object you are deleting (e.g. v1)
+    #[serde(default)]
+    pub version: String,
+}
+
+// Encode DeleteRequest as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_delete_request<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &DeleteRequest,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(8)?;
+    e.str("allClusters")?;
+    e.bool(val.all_clusters)?;
+    if let Some(val) = val.cluster.as_ref() {
+        e.str("cluster")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("group")?;
+    e.str(&val.group)?;
+    e.str("kind")?;
+    e.str(&val.kind)?;
+    e.str("name")?;
+    e.str(&val.name)?;
+    if let Some(val) = val.namespace.as_ref() {
+        e.str("namespace")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.timeout_ms.as_ref() {
+        e.str("timeoutMs")?;
+        e.u32(*val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("version")?;
+    e.str(&val.version)?;
+    Ok(())
+}
+
+// Decode DeleteRequest from cbor input stream
+#[doc(hidden)]
+pub fn decode_delete_request(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<DeleteRequest, RpcError> {
+    let __result = {
+        let mut all_clusters: Option<bool> = None;
+        let mut cluster: Option<Option<String>> = Some(None);
+        let mut group: Option<String> = None;
+        let mut kind: Option<String> = None;
+        let mut name: Option<String> = None;
+        let mut namespace: Option<Option<String>> = Some(None);
+        let mut timeout_ms: Option<Option<u32>> = Some(None);
+        let mut version: Option<String> = None;
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct DeleteRequest, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => all_clusters = Some(d.bool()?),
+                    1 => {
+                        cluster = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    2 => group = Some(d.str()?.to_string()),
+                    3 => kind = Some(d.str()?.to_string()),
+                    4 => name = Some(d.str()?.to_string()),
+                    5 => {
+                        namespace = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    6 => {
+                        timeout_ms = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.u32()?))
+                        }
+                    }
+                    7 => version = Some(d.str()?.to_string()),
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "allClusters" => all_clusters = Some(d.bool()?),
+                    "cluster" => {
+                        cluster = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "group" => group = Some(d.str()?.to_string()),
+                    "kind" => kind = Some(d.str()?.to_string()),
+                    "name" => name = Some(d.str()?.to_string()),
+                    "namespace" => {
+                        namespace = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "timeoutMs" => {
+                        timeout_ms = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.u32()?))
+                        }
+                    }
+                    "version" => version = Some(d.str()?.to_string()),
+                    _ => d.skip()?,
+                }
+            }
+        }
+        DeleteRequest {
+            all_clusters: if let Some(__x) = all_clusters {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field DeleteRequest.all_clusters (#0)".to_string(),
+                ));
+            },
+            cluster: cluster.unwrap(),
+
+            group: if let Some(__x) = group {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field DeleteRequest.group (#2)".to_string(),
+                ));
+            },
+
+            kind: if let Some(__x) = kind {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field DeleteRequest.kind (#3)".to_string(),
+                ));
+            },
+
+            name: if let Some(__x) = name {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field DeleteRequest.name (#4)".to_string(),
+                ));
+            },
+            namespace: namespace.unwrap(),
+            timeout_ms: timeout_ms.unwrap(),
+
+            version: if let Some(__x) = version {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field DeleteRequest.version (#7)".to_string(),
+                ));
+            },
+        }
+    };
+    Ok(__result)
+}
+/// The machine readable kinds of errors that can be returned in `errorKind`:
+///
+/// `RateLimited`: The request was rejected because the link exceeded its rate limit or maximum
+/// number of requests in flight. It can be retried later
+///
+/// `Timeout`: The request didn't finish within its timeout. The operation may or may not have been
+/// carried out by the API server
+///
+/// `Warnings`: The API server returned warnings and the link treats warnings as errors. The warnings
+/// are listed in `warnings`
+///
+/// `Invalid`: The object was rejected by strict field validation. The rejected fields are listed in
+/// `validationErrors`
+///
+/// `PolicyDenied`: The object was rejected by the link's policy checks before it was sent. The
+/// violated rules are listed in `policyViolations`
+///
+/// `Untrusted`: The link requires signed manifests and the manifest wasn't signed, or its signature
+/// is invalid or from a signer the link doesn't trust
+pub type ErrorKind = String;
+
+// Encode ErrorKind as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_error_kind<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ErrorKind,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.str(val)?;
+    Ok(())
+}
+
+// Decode ErrorKind from cbor input stream
+#[doc(hidden)]
+pub fn decode_error_kind(d: &mut wasmbus_rpc::cbor::Decoder<'_>) -> Result<ErrorKind, RpcError> {
+    let __result = { d.str()?.to_string() };
+    Ok(__result)
+}
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GetRequest {
+    /// The name of the cluster to fetch the object from. If not specified, the default cluster for
+    /// the link is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+    /// The group of the object you are fetching (e.g. "networking.k8s.io"). This will be an empty
+    /// string if part of `core`
+    #[serde(default)]
+    pub group: String,
+    /// The kind of the object you are fetching (e.g. Pod)
+    #[serde(default)]
+    pub kind: String,
+    /// The name of the object you are fetching
+    #[serde(default)]
+    pub name: String,
+    /// The namespace where the object is located. If not specified, the default namespace for the
+    /// context should be used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// The maximum time in milliseconds the provider may spend on this request, including time spent
+    /// waiting for rate limits. If the link has a `request_timeout`, the shorter of the two is used
+    #[serde(rename = "timeoutMs")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+    /// The API version of the object you are fetching (e.g. v1)
+    #[serde(default)]
+    pub version: String,
+}
+
+// Encode GetRequest as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_get_request<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &GetRequest,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(7)?;
+    if let Some(val) = val.cluster.as_ref() {
+        e.str("cluster")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("group")?;
+    e.str(&val.group)?;
+    e.str("kind")?;
+    e.str(&val.kind)?;
+    e.str("name")?;
+    e.str(&val.name)?;
+    if let Some(val) = val.namespace.as_ref() {
+        e.str("namespace")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.timeout_ms.as_ref() {
+        e.str("timeoutMs")?;
+        e.u32(*val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("version")?;
+    e.str(&val.version)?;
+    Ok(())
+}
+
+// Decode GetRequest from cbor input stream
+#[doc(hidden)]
+pub fn decode_get_request(d: &mut wasmbus_rpc::cbor::Decoder<'_>) -> Result<GetRequest, RpcError> {
+    let __result = {
+        let mut cluster: Option<Option<String>> = Some(None);
+        let mut group: Option<String> = None;
+        let mut kind: Option<String> = None;
+        let mut name: Option<String> = None;
+        let mut namespace: Option<Option<String>> = Some(None);
+        let mut timeout_ms: Option<Option<u32>> = Some(None);
+        let mut version: Option<String> = None;
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct GetRequest, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => {
+                        cluster = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    1 => group = Some(d.str()?.to_string()),
+                    2 => kind = Some(d.str()?.to_string()),
+                    3 => name = Some(d.str()?.to_string()),
+                    4 => {
+                        namespace = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    5 => {
+                        timeout_ms = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.u32()?))
+                        }
+                    }
+                    6 => version = Some(d.str()?.to_string()),
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "cluster" => {
+                        cluster = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "group" => group = Some(d.str()?.to_string()),
+                    "kind" => kind = Some(d.str()?.to_string()),
+                    "name" => name = Some(d.str()?.to_string()),
+                    "namespace" => {
+                        namespace = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "timeoutMs" => {
+                        timeout_ms = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.u32()?))
+                        }
+                    }
+                    "version" => version = Some(d.str()?.to_string()),
+                    _ => d.skip()?,
+                }
+            }
+        }
+        GetRequest {
+            cluster: cluster.unwrap(),
+
+            group: if let Some(__x) = group {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field GetRequest.group (#1)".to_string(),
+                ));
+            },
+
+            kind: if let Some(__x) = kind {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field GetRequest.kind (#2)".to_string(),
+                ));
+            },
+
+            name: if let Some(__x) = name {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field GetRequest.name (#3)".to_string(),
+                ));
+            },
+            namespace: namespace.unwrap(),
+            timeout_ms: timeout_ms.unwrap(),
+
+            version: if let Some(__x) = version {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field GetRequest.version (#6)".to_string(),
+                ));
+            },
+        }
+    };
+    Ok(__result)
+}
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GetResponse {
+    /// An optional message describing the error if one occurred
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// A machine readable kind for the error, if the operation failed for a reason callers may want
+    /// to handle specifically. See `ErrorKind` for the possible values
+    #[serde(rename = "errorKind")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    /// Whether the object exists
+    #[serde(default)]
+    pub found: bool,
+    /// Whether the response was served from the provider's read cache rather than the API server
+    #[serde(rename = "fromCache")]
+    #[serde(default)]
+    pub from_cache: bool,
+    /// The object encoded as JSON, if it was found
+    #[serde(with = "serde_bytes")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<Vec<u8>>,
+    /// Whether or not the operation succeeded. An object that doesn't exist is not an error
+    #[serde(default)]
+    pub succeeded: bool,
+    /// Warnings returned by the API server, e.g. for deprecated API versions or unknown fields
+    pub warnings: WarningList,
+}
+
+// Encode GetResponse as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_get_response<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &GetResponse,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(7)?;
+    if let Some(val) = val.error.as_ref() {
+        e.str("error")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.error_kind.as_ref() {
+        e.str("errorKind")?;
+        encode_error_kind(e, val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("found")?;
+    e.bool(val.found)?;
+    e.str("fromCache")?;
+    e.bool(val.from_cache)?;
+    if let Some(val) = val.object.as_ref() {
+        e.str("object")?;
+        e.bytes(val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("succeeded")?;
+    e.bool(val.succeeded)?;
+    e.str("warnings")?;
+    encode_warning_list(e, &val.warnings)?;
+    Ok(())
+}
+
+// Decode GetResponse from cbor input stream
+#[doc(hidden)]
+pub fn decode_get_response(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<GetResponse, RpcError> {
+    let __result = {
+        let mut error: Option<Option<String>> = Some(None);
+        let mut error_kind: Option<Option<ErrorKind>> = Some(None);
+        let mut found: Option<bool> = None;
+        let mut from_cache: Option<bool> = None;
+        let mut object: Option<Option<Vec<u8>>> = Some(None);
+        let mut succeeded: Option<bool> = None;
+        let mut warnings: Option<WarningList> = None;
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct GetResponse, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => {
+                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    1 => {
+                        error_kind = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_error_kind(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ErrorKind': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    2 => found = Some(d.bool()?),
+                    3 => from_cache = Some(d.bool()?),
+                    4 => {
+                        object = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.bytes()?.to_vec()))
+                        }
+                    }
+                    5 => succeeded = Some(d.bool()?),
+                    6 => {
+                        warnings = Some(decode_warning_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#WarningList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "error" => {
+                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "errorKind" => {
+                        error_kind = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_error_kind(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ErrorKind': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    "found" => found = Some(d.bool()?),
+                    "fromCache" => from_cache = Some(d.bool()?),
+                    "object" => {
+                        object = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.bytes()?.to_vec()))
+                        }
+                    }
+                    "succeeded" => succeeded = Some(d.bool()?),
+                    "warnings" => {
+                        warnings = Some(decode_warning_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#WarningList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        }
+        GetResponse {
+            error: error.unwrap(),
+            error_kind: error_kind.unwrap(),
+
+            found: if let Some(__x) = found {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field GetResponse.found (#2)".to_string(),
+                ));
+            },
+
+            from_cache: if let Some(__x) = from_cache {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field GetResponse.from_cache (#3)".to_string(),
+                ));
+            },
+            object: object.unwrap(),
+
+            succeeded: if let Some(__x) = succeeded {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field GetResponse.succeeded (#5)".to_string(),
+                ));
+            },
+
+            warnings: if let Some(__x) = warnings {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field GetResponse.warnings (#6)".to_string(),
+                ));
+            },
+        }
+    };
+    Ok(__result)
+}
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ListRequest {
+    /// The name of the cluster to list objects from. If not specified, the default cluster for the
+    /// link is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+    /// The group of the objects you are listing (e.g. "networking.k8s.io"). This will be an empty
+    /// string if part of `core`
+    #[serde(default)]
+    pub group: String,
+    /// The kind of the objects you are listing (e.g. Pod)
+    #[serde(default)]
+    pub kind: String,
+    /// A label selector to filter objects by (e.g. "app=nginx,tier!=frontend")
+    #[serde(rename = "labelSelector")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_selector: Option<String>,
+    /// The namespace to list objects in. If not specified, objects in all namespaces are listed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// The maximum time in milliseconds the provider may spend on this request, including time spent
+    /// waiting for rate limits. If the link has a `request_timeout`, the shorter of the two is used
+    #[serde(rename = "timeoutMs")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+    /// The API version of the objects you are listing (e.g. v1)
+    #[serde(default)]
+    pub version: String,
+}
+
+// Encode ListRequest as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_list_request<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ListRequest,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(7)?;
+    if let Some(val) = val.cluster.as_ref() {
+        e.str("cluster")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("group")?;
+    e.str(&val.group)?;
+    e.str("kind")?;
+    e.str(&val.kind)?;
+    if let Some(val) = val.label_selector.as_ref() {
+        e.str("labelSelector")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.namespace.as_ref() {
+        e.str("namespace")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.timeout_ms.as_ref() {
+        e.str("timeoutMs")?;
+        e.u32(*val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("version")?;
+    e.str(&val.version)?;
+    Ok(())
+}
+
+// Decode ListRequest from cbor input stream
+#[doc(hidden)]
+pub fn decode_list_request(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<ListRequest, RpcError> {
+    let __result = {
+        let mut cluster: Option<Option<String>> = Some(None);
+        let mut group: Option<String> = None;
+        let mut kind: Option<String> = None;
+        let mut label_selector: Option<Option<String>> = Some(None);
+        let mut namespace: Option<Option<String>> = Some(None);
+        let mut timeout_ms: Option<Option<u32>> = Some(None);
+        let mut version: Option<String> = None;
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct ListRequest, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => {
+                        cluster = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    1 => group = Some(d.str()?.to_string()),
+                    2 => kind = Some(d.str()?.to_string()),
+                    3 => {
+                        label_selector = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    4 => {
+                        namespace = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    5 => {
+                        timeout_ms = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.u32()?))
+                        }
+                    }
+                    6 => version = Some(d.str()?.to_string()),
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "cluster" => {
+                        cluster = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "group" => group = Some(d.str()?.to_string()),
+                    "kind" => kind = Some(d.str()?.to_string()),
+                    "labelSelector" => {
+                        label_selector = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "namespace" => {
+                        namespace = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "timeoutMs" => {
+                        timeout_ms = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.u32()?))
+                        }
+                    }
+                    "version" => version = Some(d.str()?.to_string()),
+                    _ => d.skip()?,
+                }
+            }
+        }
+        ListRequest {
+            cluster: cluster.unwrap(),
+
+            group: if let Some(__x) = group {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ListRequest.group (#1)".to_string(),
+                ));
+            },
+
+            kind: if let Some(__x) = kind {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ListRequest.kind (#2)".to_string(),
+                ));
+            },
+            label_selector: label_selector.unwrap(),
+            namespace: namespace.unwrap(),
+            timeout_ms: timeout_ms.unwrap(),
+
+            version: if let Some(__x) = version {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ListRequest.version (#6)".to_string(),
+                ));
+            },
+        }
+    };
+    Ok(__result)
+}
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ListResponse {
+    /// An optional message describing the error if one occurred
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// A machine readable kind for the error, if the operation failed for a reason callers may want
+    /// to handle specifically. See `ErrorKind` for the possible values
+    #[serde(rename = "errorKind")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    /// Whether the response was served from the provider's read cache rather than the API server
+    #[serde(rename = "fromCache")]
+    #[serde(default)]
+    pub from_cache: bool,
+    /// The matching objects, each encoded as JSON
+    pub objects: ObjectList,
+    /// Whether or not the operation succeeded
+    #[serde(default)]
+    pub succeeded: bool,
+    /// Warnings returned by the API server, e.g. for deprecated API versions or unknown fields
+    pub warnings: WarningList,
+}
+
+// Encode ListResponse as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_list_response<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ListResponse,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(6)?;
+    if let Some(val) = val.error.as_ref() {
+        e.str("error")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.error_kind.as_ref() {
+        e.str("errorKind")?;
+        encode_error_kind(e, val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("fromCache")?;
+    e.bool(val.from_cache)?;
+    e.str("objects")?;
+    encode_object_list(e, &val.objects)?;
+    e.str("succeeded")?;
+    e.bool(val.succeeded)?;
+    e.str("warnings")?;
+    encode_warning_list(e, &val.warnings)?;
+    Ok(())
+}
+
+// Decode ListResponse from cbor input stream
+#[doc(hidden)]
+pub fn decode_list_response(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<ListResponse, RpcError> {
+    let __result = {
+        let mut error: Option<Option<String>> = Some(None);
+        let mut error_kind: Option<Option<ErrorKind>> = Some(None);
+        let mut from_cache: Option<bool> = None;
+        let mut objects: Option<ObjectList> = None;
+        let mut succeeded: Option<bool> = None;
+        let mut warnings: Option<WarningList> = None;
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct ListResponse, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => {
+                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    1 => {
+                        error_kind = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_error_kind(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ErrorKind': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    2 => from_cache = Some(d.bool()?),
+                    3 => {
+                        objects = Some(decode_object_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#ObjectList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    4 => succeeded = Some(d.bool()?),
+                    5 => {
+                        warnings = Some(decode_warning_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#WarningList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "error" => {
+                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "errorKind" => {
+                        error_kind = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_error_kind(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ErrorKind': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    "fromCache" => from_cache = Some(d.bool()?),
+                    "objects" => {
+                        objects = Some(decode_object_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#ObjectList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    "succeeded" => succeeded = Some(d.bool()?),
+                    "warnings" => {
+                        warnings = Some(decode_warning_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#WarningList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        }
+        ListResponse {
+            error: error.unwrap(),
+            error_kind: error_kind.unwrap(),
+
+            from_cache: if let Some(__x) = from_cache {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ListResponse.from_cache (#2)".to_string(),
+                ));
+            },
+
+            objects: if let Some(__x) = objects {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ListResponse.objects (#3)".to_string(),
+                ));
+            },
+
+            succeeded: if let Some(__x) = succeeded {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ListResponse.succeeded (#4)".to_string(),
+                ));
+            },
+
+            warnings: if let Some(__x) = warnings {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ListResponse.warnings (#5)".to_string(),
+                ));
+            },
+        }
+    };
+    Ok(__result)
+}
+/// An ed25519 signature made with an nkey, e.g. `KeyPair::sign` from the `nkeys` crate
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ManifestSignature {
+    /// The raw signature bytes
+    #[serde(with = "serde_bytes")]
+    #[serde(default)]
+    pub signature: Vec<u8>,
+    /// The public key of the signer (e.g. `U...` or `A...`). This must be one of the link's
+    /// `trusted_signers`
+    #[serde(rename = "signerKey")]
+    #[serde(default)]
+    pub signer_key: String,
+}
+
+// Encode ManifestSignature as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_manifest_signature<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ManifestSignature,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(2)?;
+    e.str("signature")?;
+    e.bytes(&val.signature)?;
+    e.str("signerKey")?;
+    e.str(&val.signer_key)?;
+    Ok(())
+}
+
+// Decode ManifestSignature from cbor input stream
+#[doc(hidden)]
+pub fn decode_manifest_signature(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<ManifestSignature, RpcError> {
+    let __result = {
+        let mut signature: Option<Vec<u8>> = None;
+        let mut signer_key: Option<String> = None;
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct ManifestSignature, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => signature = Some(d.bytes()?.to_vec()),
+                    1 => signer_key = Some(d.str()?.to_string()),
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "signature" => signature = Some(d.bytes()?.to_vec()),
+                    "signerKey" => signer_key = Some(d.str()?.to_string()),
+                    _ => d.skip()?,
+                }
+            }
+        }
+        ManifestSignature {
+            signature: if let Some(__x) = signature {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ManifestSignature.signature (#0)".to_string(),
+                ));
+            },
+
+            signer_key: if let Some(__x) = signer_key {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ManifestSignature.signer_key (#1)".to_string(),
+                ));
+            },
+        }
+    };
+    Ok(__result)
+}
+/// A list of fields set by the provider
+pub type MutationList = Vec<String>;
+
+// Encode MutationList as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_mutation_list<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &MutationList,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.array(val.len() as u64)?;
+    for item in val.iter() {
+        e.str(item)?;
+    }
+    Ok(())
+}
+
+// Decode MutationList from cbor input stream
+#[doc(hidden)]
+pub fn decode_mutation_list(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<MutationList, RpcError> {
+    let __result = {
+        if let Some(n) = d.array()? {
+            let mut arr: Vec<String> = Vec::with_capacity(n as usize);
+            for _ in 0..(n as usize) {
+                arr.push(d.str()?.to_string())
+            }
+            arr
+        } else {
+            // indefinite array
+            let mut arr: Vec<String> = Vec::new();
+            loop {
+                match d.datatype() {
+                    Err(_) => break,
+                    Ok(wasmbus_rpc::cbor::Type::Break) => break,
+                    Ok(_) => arr.push(d.str()?.to_string()),
+                }
+            }
+            arr
+        }
+    };
+    Ok(__result)
+}
+/// A list of objects, each encoded as JSON
+pub type ObjectList = Vec<Vec<u8>>;
+
+// Encode ObjectList as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_object_list<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ObjectList,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.array(val.len() as u64)?;
+    for item in val.iter() {
+        e.bytes(item)?;
+    }
+    Ok(())
+}
+
+// Decode ObjectList from cbor input stream
+#[doc(hidden)]
+pub fn decode_object_list(d: &mut wasmbus_rpc::cbor::Decoder<'_>) -> Result<ObjectList, RpcError> {
+    let __result = {
+        if let Some(n) = d.array()? {
+            let mut arr: Vec<Vec<u8>> = Vec::with_capacity(n as usize);
+            for _ in 0..(n as usize) {
+                arr.push(d.bytes()?.to_vec())
+            }
+            arr
+        } else {
+            // indefinite array
+            let mut arr: Vec<Vec<u8>> = Vec::new();
+            loop {
+                match d.datatype() {
+                    Err(_) => break,
+                    Ok(wasmbus_rpc::cbor::Type::Break) => break,
+                    Ok(_) => arr.push(d.bytes()?.to_vec()),
+                }
+            }
+            arr
+        }
+    };
+    Ok(__result)
+}
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct OperationResponse {
+    /// The result of the operation on each cluster it was sent to. This will only contain more than
+    /// one entry if the request was sent to all clusters
+    #[serde(rename = "clusterResults")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster_results: Option<ClusterResults>,
+    /// An optional message describing the error if one occurred
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// A machine readable kind for the error, if the operation failed for a reason callers may want
+    /// to handle specifically. See `ErrorKind` for the possible values
+    #[serde(rename = "errorKind")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    /// The paths of the fields the link changed before the object was applied, i.e. defaults it filled
+    /// in (e.g. `metadata.labels.team`) and images it rewrote (e.g. `spec.containers[0].image`)
+    pub mutations: MutationList,
+    /// The policy rules the object violates, if it was denied by the provider's policy checks
+    #[serde(rename = "policyViolations")]
+    pub policy_violations: PolicyViolations,
+    /// Whether or not the operation succeeded
+    #[serde(default)]
+    pub succeeded: bool,
+    /// The fields rejected by strict field validation on any cluster
+    #[serde(rename = "validationErrors")]
+    pub validation_errors: ValidationErrors,
+    /// Warnings returned by the API server on any cluster, e.g. for deprecated API versions or
+    /// unknown fields
+    pub warnings: WarningList,
+}
+
+// Encode OperationResponse as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_operation_response<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &OperationResponse,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.map(8)?;
+    if let Some(val) = val.cluster_results.as_ref() {
+        e.str("clusterResults")?;
+        encode_cluster_results(e, val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.error.as_ref() {
+        e.str("error")?;
+        e.str(val)?;
+    } else {
+        e.null()?;
+    }
+    if let Some(val) = val.error_kind.as_ref() {
+        e.str("errorKind")?;
+        encode_error_kind(e, val)?;
+    } else {
+        e.null()?;
+    }
+    e.str("mutations")?;
+    encode_mutation_list(e, &val.mutations)?;
+    e.str("policyViolations")?;
+    encode_policy_violations(e, &val.policy_violations)?;
+    e.str("succeeded")?;
+    e.bool(val.succeeded)?;
+    e.str("validationErrors")?;
+    encode_validation_errors(e, &val.validation_errors)?;
+    e.str("warnings")?;
+    encode_warning_list(e, &val.warnings)?;
+    Ok(())
+}
+
+// Decode OperationResponse from cbor input stream
+#[doc(hidden)]
+pub fn decode_operation_response(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<OperationResponse, RpcError> {
+    let __result = {
+        let mut cluster_results: Option<Option<ClusterResults>> = Some(None);
+        let mut error: Option<Option<String>> = Some(None);
+        let mut error_kind: Option<Option<ErrorKind>> = Some(None);
+        let mut mutations: Option<MutationList> = None;
+        let mut policy_violations: Option<PolicyViolations> = None;
+        let mut succeeded: Option<bool> = None;
+        let mut validation_errors: Option<ValidationErrors> = None;
+        let mut warnings: Option<WarningList> = None;
+
+        let is_array = match d.datatype()? {
+            wasmbus_rpc::cbor::Type::Array => true,
+            wasmbus_rpc::cbor::Type::Map => false,
+            _ => {
+                return Err(RpcError::Deser(
+                    "decoding struct OperationResponse, expected array or map".to_string(),
+                ))
+            }
+        };
+        if is_array {
+            let len = d.fixed_array()?;
+            for __i in 0..(len as usize) {
+                match __i {
+                    0 => {
+                        cluster_results = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_cluster_results(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ClusterResults': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    1 => {
+                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    2 => {
+                        error_kind = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_error_kind(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ErrorKind': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    3 => {
+                        mutations = Some(decode_mutation_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#MutationList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    4 => {
+                        policy_violations = Some(decode_policy_violations(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#PolicyViolations': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    5 => succeeded = Some(d.bool()?),
+                    6 => {
+                        validation_errors = Some(decode_validation_errors(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#ValidationErrors': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    7 => {
+                        warnings = Some(decode_warning_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#WarningList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        } else {
+            let len = d.fixed_map()?;
+            for __i in 0..(len as usize) {
+                match d.str()? {
+                    "clusterResults" => {
+                        cluster_results = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_cluster_results(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ClusterResults': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    "error" => {
+                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(d.str()?.to_string()))
+                        }
+                    }
+                    "errorKind" => {
+                        error_kind = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
+                            d.skip()?;
+                            Some(None)
+                        } else {
+                            Some(Some(decode_error_kind(d).map_err(|e| {
+                                format!(
+                                    "decoding 'com.cosmonic.kubernetesapplier#ErrorKind': {}",
+                                    e
+                                )
+                            })?))
+                        }
+                    }
+                    "mutations" => {
+                        mutations = Some(decode_mutation_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#MutationList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    "policyViolations" => {
+                        policy_violations = Some(decode_policy_violations(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#PolicyViolations': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    "succeeded" => succeeded = Some(d.bool()?),
+                    "validationErrors" => {
+                        validation_errors = Some(decode_validation_errors(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#ValidationErrors': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    "warnings" => {
+                        warnings = Some(decode_warning_list(d).map_err(|e| {
+                            format!(
+                                "decoding 'com.cosmonic.kubernetesapplier#WarningList': {}",
+                                e
+                            )
+                        })?)
+                    }
+                    _ => d.skip()?,
+                }
+            }
+        }
+        OperationResponse {
+            cluster_results: cluster_results.unwrap(),
+            error: error.unwrap(),
+            error_kind: error_kind.unwrap(),
+
+            mutations: if let Some(__x) = mutations {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field OperationResponse.mutations (#3)".to_string(),
+                ));
+            },
+
+            policy_violations: if let Some(__x) = policy_violations {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field OperationResponse.policy_violations (#4)".to_string(),
+                ));
+            },
+
+            succeeded: if let Some(__x) = succeeded {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field OperationResponse.succeeded (#5)".to_string(),
+                ));
+            },
+
+            validation_errors: if let Some(__x) = validation_errors {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field OperationResponse.validation_errors (#6)".to_string(),
+                ));
+            },
+
+            warnings: if let Some(__x) = warnings {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field OperationResponse.warnings (#7)".to_string(),
+                ));
+            },
+        }
+    };
+    Ok(__result)
+}
+/// A single policy rule an object violates
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PolicyViolation {
+    /// The path of the offending field in the manifest (e.g. `spec.template.spec.hostNetwork`)
     #[serde(default)]
-    pub kind: String,
-    /// The name of the object you are deleting
+    pub field: String,
+    /// A description of the violation
     #[serde(default)]
-    pub name: String,
-    /// The namespace where the object you want to delete is located. If not specified, the default
-    /// namespace for the context should be used
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub namespace: Option<String>,
-    /// The API version of the object you are deleting (e.g. v1)
+    pub message: String,
+    /// The name of the rule (e.g. `privileged` or `allowedRegistries`)
     #[serde(default)]
-    pub version: String,
+    pub policy: String,
 }
 
-// Encode DeleteRequest as CBOR and append to output stream
+// Encode PolicyViolation as CBOR and append to output stream
 #[doc(hidden)]
 #[allow(unused_mut)]
-pub fn encode_delete_request<W: wasmbus_rpc::cbor::Write>(
+pub fn encode_policy_violation<W: wasmbus_rpc::cbor::Write>(
     mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
-    val: &DeleteRequest,
+    val: &PolicyViolation,
 ) -> RpcResult<()>
 where
     <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
 {
-    e.map(5)?;
-    e.str("group")?;
-    e.str(&val.group)?;
-    e.str("kind")?;
-    e.str(&val.kind)?;
-    e.str("name")?;
-    e.str(&val.name)?;
-    if let Some(val) = val.namespace.as_ref() {
-        e.str("namespace")?;
-        e.str(val)?;
-    } else {
-        e.null()?;
-    }
-    e.str("version")?;
-    e.str(&val.version)?;
+    e.map(3)?;
+    e.str("field")?;
+    e.str(&val.field)?;
+    e.str("message")?;
+    e.str(&val.message)?;
+    e.str("policy")?;
+    e.str(&val.policy)?;
     Ok(())
 }
 
-// Decode DeleteRequest from cbor input stream
+// Decode PolicyViolation from cbor input stream
 #[doc(hidden)]
-pub fn decode_delete_request(
+pub fn decode_policy_violation(
     d: &mut wasmbus_rpc::cbor::Decoder<'_>,
-) -> Result<DeleteRequest, RpcError> {
+) -> Result<PolicyViolation, RpcError> {
     let __result = {
-        let mut group: Option<String> = None;
-        let mut kind: Option<String> = None;
-        let mut name: Option<String> = None;
-        let mut namespace: Option<Option<String>> = Some(None);
-        let mut version: Option<String> = None;
+        let mut field: Option<String> = None;
+        let mut message: Option<String> = None;
+        let mut policy: Option<String> = None;
 
         let is_array = match d.datatype()? {
             wasmbus_rpc::cbor::Type::Array => true,
             wasmbus_rpc::cbor::Type::Map => false,
             _ => {
                 return Err(RpcError::Deser(
-                    "decoding struct DeleteRequest, expected array or map".to_string(),
+                    "decoding struct PolicyViolation, expected array or map".to_string(),
                 ))
             }
         };
@@ -96,18 +2221,9 @@ pub fn decode_delete_request(
             let len = d.fixed_array()?;
             for __i in 0..(len as usize) {
                 match __i {
-                    0 => group = Some(d.str()?.to_string()),
-                    1 => kind = Some(d.str()?.to_string()),
-                    2 => name = Some(d.str()?.to_string()),
-                    3 => {
-                        namespace = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
-                            d.skip()?;
-                            Some(None)
-                        } else {
-                            Some(Some(d.str()?.to_string()))
-                        }
-                    }
-                    4 => version = Some(d.str()?.to_string()),
+                    0 => field = Some(d.str()?.to_string()),
+                    1 => message = Some(d.str()?.to_string()),
+                    2 => policy = Some(d.str()?.to_string()),
                     _ => d.skip()?,
                 }
             }
@@ -115,106 +2231,142 @@ pub fn decode_delete_request(
             let len = d.fixed_map()?;
             for __i in 0..(len as usize) {
                 match d.str()? {
-                    "group" => group = Some(d.str()?.to_string()),
-                    "kind" => kind = Some(d.str()?.to_string()),
-                    "name" => name = Some(d.str()?.to_string()),
-                    "namespace" => {
-                        namespace = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
-                            d.skip()?;
-                            Some(None)
-                        } else {
-                            Some(Some(d.str()?.to_string()))
-                        }
-                    }
-                    "version" => version = Some(d.str()?.to_string()),
+                    "field" => field = Some(d.str()?.to_string()),
+                    "message" => message = Some(d.str()?.to_string()),
+                    "policy" => policy = Some(d.str()?.to_string()),
                     _ => d.skip()?,
                 }
             }
         }
-        DeleteRequest {
-            group: if let Some(__x) = group {
+        PolicyViolation {
+            field: if let Some(__x) = field {
                 __x
             } else {
                 return Err(RpcError::Deser(
-                    "missing field DeleteRequest.group (#0)".to_string(),
+                    "missing field PolicyViolation.field (#0)".to_string(),
                 ));
             },
 
-            kind: if let Some(__x) = kind {
+            message: if let Some(__x) = message {
                 __x
             } else {
                 return Err(RpcError::Deser(
-                    "missing field DeleteRequest.kind (#1)".to_string(),
+                    "missing field PolicyViolation.message (#1)".to_string(),
                 ));
             },
 
-            name: if let Some(__x) = name {
+            policy: if let Some(__x) = policy {
                 __x
             } else {
                 return Err(RpcError::Deser(
-                    "missing field DeleteRequest.name (#2)".to_string(),
+                    "missing field PolicyViolation.policy (#2)".to_string(),
                 ));
             },
-            namespace: namespace.unwrap(),
+        }
+    };
+    Ok(__result)
+}
+/// A list of policy rules an object violates
+pub type PolicyViolations = Vec<PolicyViolation>;
 
-            version: if let Some(__x) = version {
-                __x
-            } else {
-                return Err(RpcError::Deser(
-                    "missing field DeleteRequest.version (#4)".to_string(),
-                ));
-            },
+// Encode PolicyViolations as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_policy_violations<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &PolicyViolations,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.array(val.len() as u64)?;
+    for item in val.iter() {
+        encode_policy_violation(e, item)?;
+    }
+    Ok(())
+}
+
+// Decode PolicyViolations from cbor input stream
+#[doc(hidden)]
+pub fn decode_policy_violations(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<PolicyViolations, RpcError> {
+    let __result = {
+        if let Some(n) = d.array()? {
+            let mut arr: Vec<PolicyViolation> = Vec::with_capacity(n as usize);
+            for _ in 0..(n as usize) {
+                arr.push(decode_policy_violation(d).map_err(|e| {
+                    format!(
+                        "decoding 'com.cosmonic.kubernetesapplier#PolicyViolation': {}",
+                        e
+                    )
+                })?)
+            }
+            arr
+        } else {
+            // indefinite array
+            let mut arr: Vec<PolicyViolation> = Vec::new();
+            loop {
+                match d.datatype() {
+                    Err(_) => break,
+                    Ok(wasmbus_rpc::cbor::Type::Break) => break,
+                    Ok(_) => arr.push(decode_policy_violation(d).map_err(|e| {
+                        format!(
+                            "decoding 'com.cosmonic.kubernetesapplier#PolicyViolation': {}",
+                            e
+                        )
+                    })?),
+                }
+            }
+            arr
         }
     };
     Ok(__result)
 }
+/// A single field rejected by strict field validation
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
-pub struct OperationResponse {
-    /// An optional message describing the error if one occurred
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub error: Option<String>,
-    /// Whether or not the operation succeeded
+pub struct ValidationError {
+    /// The path of the field in the manifest (e.g. `spec.replcas`)
     #[serde(default)]
-    pub succeeded: bool,
+    pub field: String,
+    /// Why the field was rejected (e.g. `unknown field`, `duplicate field`)
+    #[serde(default)]
+    pub reason: String,
 }
 
-// Encode OperationResponse as CBOR and append to output stream
+// Encode ValidationError as CBOR and append to output stream
 #[doc(hidden)]
 #[allow(unused_mut)]
-pub fn encode_operation_response<W: wasmbus_rpc::cbor::Write>(
+pub fn encode_validation_error<W: wasmbus_rpc::cbor::Write>(
     mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
-    val: &OperationResponse,
+    val: &ValidationError,
 ) -> RpcResult<()>
 where
     <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
 {
     e.map(2)?;
-    if let Some(val) = val.error.as_ref() {
-        e.str("error")?;
-        e.str(val)?;
-    } else {
-        e.null()?;
-    }
-    e.str("succeeded")?;
-    e.bool(val.succeeded)?;
+    e.str("field")?;
+    e.str(&val.field)?;
+    e.str("reason")?;
+    e.str(&val.reason)?;
     Ok(())
 }
 
-// Decode OperationResponse from cbor input stream
+// Decode ValidationError from cbor input stream
 #[doc(hidden)]
-pub fn decode_operation_response(
+pub fn decode_validation_error(
     d: &mut wasmbus_rpc::cbor::Decoder<'_>,
-) -> Result<OperationResponse, RpcError> {
+) -> Result<ValidationError, RpcError> {
     let __result = {
-        let mut error: Option<Option<String>> = Some(None);
-        let mut succeeded: Option<bool> = None;
+        let mut field: Option<String> = None;
+        let mut reason: Option<String> = None;
 
         let is_array = match d.datatype()? {
             wasmbus_rpc::cbor::Type::Array => true,
             wasmbus_rpc::cbor::Type::Map => false,
             _ => {
                 return Err(RpcError::Deser(
-                    "decoding struct OperationResponse, expected array or map".to_string(),
+                    "decoding struct ValidationError, expected array or map".to_string(),
                 ))
             }
         };
@@ -222,15 +2374,8 @@ pub fn decode_operation_response(
             let len = d.fixed_array()?;
             for __i in 0..(len as usize) {
                 match __i {
-                    0 => {
-                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
-                            d.skip()?;
-                            Some(None)
-                        } else {
-                            Some(Some(d.str()?.to_string()))
-                        }
-                    }
-                    1 => succeeded = Some(d.bool()?),
+                    0 => field = Some(d.str()?.to_string()),
+                    1 => reason = Some(d.str()?.to_string()),
                     _ => d.skip()?,
                 }
             }
@@ -238,35 +2383,138 @@ pub fn decode_operation_response(
             let len = d.fixed_map()?;
             for __i in 0..(len as usize) {
                 match d.str()? {
-                    "error" => {
-                        error = if wasmbus_rpc::cbor::Type::Null == d.datatype()? {
-                            d.skip()?;
-                            Some(None)
-                        } else {
-                            Some(Some(d.str()?.to_string()))
-                        }
-                    }
-                    "succeeded" => succeeded = Some(d.bool()?),
+                    "field" => field = Some(d.str()?.to_string()),
+                    "reason" => reason = Some(d.str()?.to_string()),
                     _ => d.skip()?,
                 }
             }
         }
-        OperationResponse {
-            error: error.unwrap(),
+        ValidationError {
+            field: if let Some(__x) = field {
+                __x
+            } else {
+                return Err(RpcError::Deser(
+                    "missing field ValidationError.field (#0)".to_string(),
+                ));
+            },
 
-            succeeded: if let Some(__x) = succeeded {
+            reason: if let Some(__x) = reason {
                 __x
             } else {
                 return Err(RpcError::Deser(
-                    "missing field OperationResponse.succeeded (#1)".to_string(),
+                    "missing field ValidationError.reason (#1)".to_string(),
                 ));
             },
         }
     };
     Ok(__result)
 }
-/// The KubernetesApplier service has a two methods, one to apply an object (that can be a create or
-/// update) and to delete an object
+/// A list of fields rejected by field validation
+pub type ValidationErrors = Vec<ValidationError>;
+
+// Encode ValidationErrors as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_validation_errors<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &ValidationErrors,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.array(val.len() as u64)?;
+    for item in val.iter() {
+        encode_validation_error(e, item)?;
+    }
+    Ok(())
+}
+
+// Decode ValidationErrors from cbor input stream
+#[doc(hidden)]
+pub fn decode_validation_errors(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<ValidationErrors, RpcError> {
+    let __result = {
+        if let Some(n) = d.array()? {
+            let mut arr: Vec<ValidationError> = Vec::with_capacity(n as usize);
+            for _ in 0..(n as usize) {
+                arr.push(decode_validation_error(d).map_err(|e| {
+                    format!(
+                        "decoding 'com.cosmonic.kubernetesapplier#ValidationError': {}",
+                        e
+                    )
+                })?)
+            }
+            arr
+        } else {
+            // indefinite array
+            let mut arr: Vec<ValidationError> = Vec::new();
+            loop {
+                match d.datatype() {
+                    Err(_) => break,
+                    Ok(wasmbus_rpc::cbor::Type::Break) => break,
+                    Ok(_) => arr.push(decode_validation_error(d).map_err(|e| {
+                        format!(
+                            "decoding 'com.cosmonic.kubernetesapplier#ValidationError': {}",
+                            e
+                        )
+                    })?),
+                }
+            }
+            arr
+        }
+    };
+    Ok(__result)
+}
+/// A list of warnings returned by the API server
+pub type WarningList = Vec<String>;
+
+// Encode WarningList as CBOR and append to output stream
+#[doc(hidden)]
+#[allow(unused_mut)]
+pub fn encode_warning_list<W: wasmbus_rpc::cbor::Write>(
+    mut e: &mut wasmbus_rpc::cbor::Encoder<W>,
+    val: &WarningList,
+) -> RpcResult<()>
+where
+    <W as wasmbus_rpc::cbor::Write>::Error: std::fmt::Display,
+{
+    e.array(val.len() as u64)?;
+    for item in val.iter() {
+        e.str(item)?;
+    }
+    Ok(())
+}
+
+// Decode WarningList from cbor input stream
+#[doc(hidden)]
+pub fn decode_warning_list(
+    d: &mut wasmbus_rpc::cbor::Decoder<'_>,
+) -> Result<WarningList, RpcError> {
+    let __result = {
+        if let Some(n) = d.array()? {
+            let mut arr: Vec<String> = Vec::with_capacity(n as usize);
+            for _ in 0..(n as usize) {
+                arr.push(d.str()?.to_string())
+            }
+            arr
+        } else {
+            // indefinite array
+            let mut arr: Vec<String> = Vec::new();
+            loop {
+                match d.datatype() {
+                    Err(_) => break,
+                    Ok(wasmbus_rpc::cbor::Type::Break) => break,
+                    Ok(_) => arr.push(d.str()?.to_string()),
+                }
+            }
+            arr
+        }
+    };
+    Ok(__result)
+}
+/// The KubernetesApplier service has methods to apply an object (that can be a create or update), to
+/// delete an object and to read objects back
 /// wasmbus.contractId: cosmonic:kubernetes_applier
 /// wasmbus.providerReceive
 #[async_trait]
@@ -276,15 +2524,20 @@ pub trait KubernetesApplier {
         "cosmonic:kubernetes_applier"
     }
     /// Attempts to create or update the arbitrary object it is given
-    async fn apply(&self, ctx: &Context, arg: &Vec<u8>) -> RpcResult<OperationResponse>;
+    async fn apply(&self, ctx: &Context, arg: &ApplyRequest) -> RpcResult<OperationResponse>;
     /// Attempts to delete an object with the given GVK (group, version, kind), name, and namespace.
     /// This should be idempotent, meaning that it should return successful if the object doesn't exist
     async fn delete(&self, ctx: &Context, arg: &DeleteRequest) -> RpcResult<OperationResponse>;
+    /// Fetches a single object with the given GVK (group, version, kind), name, and namespace
+    async fn get(&self, ctx: &Context, arg: &GetRequest) -> RpcResult<GetResponse>;
+    /// Lists all objects with the given GVK (group, version, kind), optionally filtered by namespace and
+    /// label selector
+    async fn list(&self, ctx: &Context, arg: &ListRequest) -> RpcResult<ListResponse>;
 }
 
 /// KubernetesApplierReceiver receives messages defined in the KubernetesApplier service trait
-/// The KubernetesApplier service has a two methods, one to apply an object (that can be a create or
-/// update) and to delete an object
+/// The KubernetesApplier service has methods to apply an object (that can be a create or update), to
+/// delete an object and to read objects back
 #[doc(hidden)]
 #[async_trait]
 pub trait KubernetesApplierReceiver: MessageDispatch + KubernetesApplier {
@@ -295,8 +2548,8 @@ pub trait KubernetesApplierReceiver: MessageDispatch + KubernetesApplier {
     ) -> Result<Message<'msg__>, RpcError> {
         match message.method {
             "Apply" => {
-                let value: Vec<u8> = wasmbus_rpc::common::deserialize(&message.arg)
-                    .map_err(|e| RpcError::Deser(format!("'Blob': {}", e)))?;
+                let value: ApplyRequest = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'ApplyRequest': {}", e)))?;
 
                 let resp = KubernetesApplier::apply(self, ctx, &value).await?;
                 let buf = wasmbus_rpc::common::serialize(&resp)?;
@@ -318,6 +2571,30 @@ pub trait KubernetesApplierReceiver: MessageDispatch + KubernetesApplier {
                     arg: Cow::Owned(buf),
                 })
             }
+            "Get" => {
+                let value: GetRequest = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'GetRequest': {}", e)))?;
+
+                let resp = KubernetesApplier::get(self, ctx, &value).await?;
+                let buf = wasmbus_rpc::common::serialize(&resp)?;
+
+                Ok(Message {
+                    method: "KubernetesApplier.Get",
+                    arg: Cow::Owned(buf),
+                })
+            }
+            "List" => {
+                let value: ListRequest = wasmbus_rpc::common::deserialize(&message.arg)
+                    .map_err(|e| RpcError::Deser(format!("'ListRequest': {}", e)))?;
+
+                let resp = KubernetesApplier::list(self, ctx, &value).await?;
+                let buf = wasmbus_rpc::common::serialize(&resp)?;
+
+                Ok(Message {
+                    method: "KubernetesApplier.List",
+                    arg: Cow::Owned(buf),
+                })
+            }
             _ => Err(RpcError::MethodNotHandled(format!(
                 "KubernetesApplier::{}",
                 message.method
@@ -327,8 +2604,8 @@ pub trait KubernetesApplierReceiver: MessageDispatch + KubernetesApplier {
 }
 
 /// KubernetesApplierSender sends messages to a KubernetesApplier service
-/// The KubernetesApplier service has a two methods, one to apply an object (that can be a create or
-/// update) and to delete an object
+/// The KubernetesApplier service has methods to apply an object (that can be a create or update), to
+/// delete an object and to read objects back
 /// client for sending KubernetesApplier messages
 #[derive(Debug)]
 pub struct KubernetesApplierSender<T: Transport> {
@@ -375,7 +2652,7 @@ impl<T: Transport + std::marker::Sync + std::marker::Send> KubernetesApplier
 {
     #[allow(unused)]
     /// Attempts to create or update the arbitrary object it is given
-    async fn apply(&self, ctx: &Context, arg: &Vec<u8>) -> RpcResult<OperationResponse> {
+    async fn apply(&self, ctx: &Context, arg: &ApplyRequest) -> RpcResult<OperationResponse> {
         let buf = wasmbus_rpc::common::serialize(arg)?;
 
         let resp = self
@@ -416,4 +2693,47 @@ impl<T: Transport + std::marker::Sync + std::marker::Send> KubernetesApplier
             .map_err(|e| RpcError::Deser(format!("'{}': OperationResponse", e)))?;
         Ok(value)
     }
+    #[allow(unused)]
+    /// Fetches a single object with the given GVK (group, version, kind), name, and namespace
+    async fn get(&self, ctx: &Context, arg: &GetRequest) -> RpcResult<GetResponse> {
+        let buf = wasmbus_rpc::common::serialize(arg)?;
+
+        let resp = self
+            .transport
+            .send(
+                ctx,
+                Message {
+                    method: "KubernetesApplier.Get",
+                    arg: Cow::Borrowed(&buf),
+                },
+                None,
+            )
+            .await?;
+
+        let value: GetResponse = wasmbus_rpc::common::deserialize(&resp)
+            .map_err(|e| RpcError::Deser(format!("'{}': GetResponse", e)))?;
+        Ok(value)
+    }
+    #[allow(unused)]
+    /// Lists all objects with the given GVK (group, version, kind), optionally filtered by namespace and
+    /// label selector
+    async fn list(&self, ctx: &Context, arg: &ListRequest) -> RpcResult<ListResponse> {
+        let buf = wasmbus_rpc::common::serialize(arg)?;
+
+        let resp = self
+            .transport
+            .send(
+                ctx,
+                Message {
+                    method: "KubernetesApplier.List",
+                    arg: Cow::Borrowed(&buf),
+                },
+                None,
+            )
+            .await?;
+
+        let value: ListResponse = wasmbus_rpc::common::deserialize(&resp)
+            .map_err(|e| RpcError::Deser(format!("'{}': ListResponse", e)))?;
+        Ok(value)
+    }
 }
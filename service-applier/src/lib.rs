@@ -8,7 +8,9 @@ use k8s_openapi::{
     apimachinery::pkg::{apis::meta::v1::ObjectMeta, util::intstr::IntOrString},
     Resource,
 };
-use kubernetes_applier_interface::{DeleteRequest, KubernetesApplier, KubernetesApplierSender};
+use kubernetes_applier_interface::{
+    ApplyRequest, DeleteRequest, KubernetesApplier, KubernetesApplierSender,
+};
 use wasmbus_rpc::{actor::prelude::*, core::LinkDefinition};
 use wasmcloud_interface_logging::debug;
 use wasmcloud_interface_messaging::{MessageSubscriber, MessageSubscriberReceiver, SubMessage};
@@ -136,28 +138,33 @@ async fn handle_apply(ctx: &Context, ld: LinkDefinition) -> RpcResult<()> {
     // some sort of data store that maps a unique service name to the full link definition. For
     // here, you can only have one linkdef of this type for an actor, so we just use the lowercased
     // actor key
+    let manifest = serde_yaml::to_vec(&Service {
+        metadata: ObjectMeta {
+            name: Some(svc_name),
+            labels: Some(labels),
+            ..Default::default()
+        },
+        spec: Some(ServiceSpec {
+            selector: Some(selector),
+            ports: Some(vec![ServicePort {
+                protocol: Some("TCP".to_string()),
+                port,
+                target_port: Some(IntOrString::Int(port)),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+    .expect("Unable to serialize Service to yaml. This is programmer error");
+
     let resp = sender
         .apply(
             ctx,
-            &serde_yaml::to_vec(&Service {
-                metadata: ObjectMeta {
-                    name: Some(svc_name),
-                    labels: Some(labels),
-                    ..Default::default()
-                },
-                spec: Some(ServiceSpec {
-                    selector: Some(selector),
-                    ports: Some(vec![ServicePort {
-                        protocol: Some("TCP".to_string()),
-                        port,
-                        target_port: Some(IntOrString::Int(port)),
-                        ..Default::default()
-                    }]),
-                    ..Default::default()
-                }),
+            &ApplyRequest {
+                manifest,
                 ..Default::default()
-            })
-            .expect("Unable to serialize Service to yaml. This is programmer error"),
+            },
         )
         .await?;
 
@@ -188,7 +195,7 @@ async fn handle_delete(ctx: &Context, ld: LinkDefinition) -> RpcResult<()> {
                 kind: Service::KIND.to_owned(),
                 version: Service::VERSION.to_owned(),
                 name: svc_name,
-                namespace: None,
+                ..Default::default()
             },
         )
         .await?;